@@ -1,16 +1,51 @@
 //! Directory operations
 
-use core::{convert::TryFrom, fmt, mem, ops::IndexMut, slice};
+use core::{cmp, convert::TryFrom, fmt, mem, ops::IndexMut, slice};
+use fallible::TryClone;
+use libc;
 use rand::Rng;
 
 use {Error, File, Str};
-use file::{FileMode, Perm};
+use file::{AtFlags, FileMode, FileType, OpenFlags, Perm, Stat, from_opt_dir, stat_at, unlink_at};
+#[cfg(target_os = "linux")]
+use file::{RenameFlags, rename_at2};
 
 /// Create a directory at the given `path`.
 pub fn mkdir_at(opt_dir: Option<&File>, path: &Str, mode: FileMode) -> Result<(), Error> {
     unsafe { esyscall_!(MKDIRAT, ::file::from_opt_dir(opt_dir), path.as_ptr(), mode.bits()) }
 }
 
+/// Open the directory at the given `path`.
+///
+/// Fails with [`ENOTDIR`](../struct.Error.html#associatedconstant.ENOTDIR) if the file there is not a directory.
+#[inline]
+pub fn open_at(opt_dir: Option<&File>, path: &Str, flags: OpenFlags) -> Result<File, Error> {
+    open_dir(opt_dir, path, flags.bits())
+}
+
+#[inline]
+fn open_dir(opt_dir: Option<&File>, path: &Str, flags: usize) -> Result<File, Error> {
+    unsafe { esyscall!(OPENAT, from_opt_dir(opt_dir), path.as_ptr(),
+                       libc::O_RDONLY as usize | libc::O_DIRECTORY as usize | flags) }
+        .map(|fd| File::new_unchecked(fd as _))
+}
+
+/// Remove the directory at the given `path`, which must be empty.
+#[inline]
+pub fn rmdir_at(opt_dir: Option<&File>, path: &Str) -> Result<(), Error> {
+    unsafe { esyscall_!(UNLINKAT, from_opt_dir(opt_dir), path.as_ptr(), libc::AT_REMOVEDIR) }
+}
+
+/// Atomically exchange the files at `path_a` and `path_b`, e.g. to swap a newly-built directory tree into place.
+///
+/// Both must exist, but need not be directories: files of any types, even of different types, are exchanged.
+#[cfg(target_os = "linux")]
+#[inline]
+pub fn exchange_at(opt_dir_a: Option<&File>, path_a: &Str,
+                   opt_dir_b: Option<&File>, path_b: &Str) -> Result<(), Error> {
+    rename_at2(opt_dir_a, path_a, opt_dir_b, path_b, RenameFlags::Exchange)
+}
+
 /// Generate a unique temporary file name in `templ`, and create a directory there.
 ///
 /// The given `range` of `templ` will be replaced with a string which uniquifies the file name. The contents of `range` after the call are unspecified.
@@ -66,14 +101,18 @@ impl Entries {
             len: u16,
         }
 
+        // The name follows `len` immediately, not at `size_of::<Hdr>()`, which is padded.
+        const name_off: usize = 2 * mem::size_of::<usize>() + mem::size_of::<u16>();
+
         Ok(Some(unsafe {
             let k = self.k;
             let hdr = *(self.buf.as_ptr().wrapping_add(k) as *const Hdr);
-            self.k += hdr.off;
-            let typ = self.buf[k + hdr.len as usize - 1];
+            self.k += hdr.len as usize;
+            let typ = FileType::from_dirent_type(self.buf[k + hdr.len as usize - 1]);
+            let name = &slice::from_raw_parts(self.buf.as_ptr().wrapping_add(k),
+                                              hdr.len as usize - 1)[name_off..];
             Entry { ino: hdr.ino, typ,
-                    name: &slice::from_raw_parts(self.buf.as_ptr().wrapping_add(k),
-                                                 hdr.len as usize - 2)[mem::size_of::<Hdr>()..] }
+                    name: &name[..name.iter().position(|&b| 0 == b).unwrap_or(name.len())] }
         }))
     }
 }
@@ -84,8 +123,141 @@ impl Entries {
 pub struct Entry<'a> {
     /// Inode number
     pub ino: usize,
-    /// File type
-    pub typ: u8,
+    /// File type, which may be `Unknown` if the filesystem does not report it
+    pub typ: FileType,
     /// File name
     pub name: &'a [u8],
 }
+
+impl<'a> Entry<'a> {
+    /// Return information about the file of the entry, which is in `dir`.
+    #[inline]
+    pub fn stat_at(&self, dir: &File, at_flags: AtFlags) -> Result<Stat, Error> {
+        with_name_str(self.name, |name| stat_at(Some(dir), name, at_flags))?
+    }
+
+    /// Return the type of the file of the entry, which is in `dir`.
+    ///
+    /// If the filesystem did not report it, this finds it out by `stat_at`.
+    #[inline]
+    pub fn file_type_at(&self, dir: &File) -> Result<FileType, Error> {
+        match self.typ {
+            FileType::Unknown => self.stat_at(dir, AtFlags::empty()).map(|st| st.typ),
+            typ => Ok(typ),
+        }
+    }
+}
+
+/// What to do after visiting an entry in a walk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Walk {
+    /** Go on, descending into the entry if it is a directory */ Continue,
+    /** Go on, but do not descend into the entry              */ Prune,
+    /** Stop the walk                                         */ Stop,
+}
+
+/// Entry visited in a walk
+#[derive(Debug)]
+pub struct WalkEntry<'a> {
+    /// Directory containing the entry, against which to resolve its name in `_at` calls
+    pub dir: &'a File,
+    /// The entry
+    pub entry: &'a Entry<'a>,
+    /// Depth of the entry: 0 for entries of the directory at which the walk started
+    pub depth: usize,
+    /// Whether this is the second visit of a directory, after the walk descended into it
+    pub post: bool,
+}
+
+/// Greatest depth to which a walk descends, whatever `max_depth` is given, as each level takes some 4 KiB of stack
+pub const MAX_WALK_DEPTH: usize = 0x100;
+
+/// Walk the tree of the directory at `path`, calling `f` on each entry except `.` and `..`.
+///
+/// The entries' types are known, even if the filesystem does not report them, and for followed links are the types of the targets.
+/// Each directory is visited before its entries, and, if the walk descended into it, again after them, with `post` set.
+/// The walk descends no deeper than `max_depth` directories below `path`, nor than `MAX_WALK_DEPTH`.
+/// Entries removed while the walk is at them are skipped.
+/// Symbolic links, including `path` itself, are not followed unless `at_flags` contains `Follow`;
+/// if they are, a cycle of links may make the walk go on until it reaches `max_depth`.
+///
+/// The walk stops at the first failure, whether of `f` or of the walk itself.
+pub fn walk_at<F: FnMut(&WalkEntry) -> Result<Walk, Error>>
+  (opt_dir: Option<&File>, path: &Str, max_depth: usize, at_flags: AtFlags, mut f: F) -> Result<(), Error> {
+    let dir = open_dir(opt_dir, path, nofollow(at_flags))?;
+    walk_in(&dir, 0, cmp::min(max_depth, MAX_WALK_DEPTH), at_flags, &mut f).map(|_| ())
+}
+
+fn walk_in<F: FnMut(&WalkEntry) -> Result<Walk, Error>>
+  (dir: &File, depth: usize, max_depth: usize, at_flags: AtFlags, f: &mut F) -> Result<Walk, Error> {
+    let mut entries = Entries::try_from(dir.try_clone()?)?;
+    while let Some(entry) = entries.next()? {
+        if b"." == entry.name || b".." == entry.name { continue }
+        let typ = match entry.typ {
+            FileType::Symlink if at_flags.contains(AtFlags::Follow) => match entry.stat_at(dir, AtFlags::Follow) {
+                Ok(st) => st.typ,
+                Err(Error::ENOENT) | Err(Error::ELOOP) => FileType::Symlink,
+                Err(e) => return Err(e),
+            },
+            _ => match entry.file_type_at(dir) {
+                Ok(typ) => typ,
+                Err(Error::ENOENT) => continue,
+                Err(e) => return Err(e),
+            },
+        };
+        let entry = Entry { typ, ..entry };
+        match f(&WalkEntry { dir, entry: &entry, depth, post: false })? {
+            Walk::Stop => return Ok(Walk::Stop),
+            Walk::Prune => continue,
+            Walk::Continue => (),
+        }
+        if FileType::Directory == typ && depth < max_depth {
+            let sub = match with_name_str(entry.name, |name| open_dir(Some(dir), name, nofollow(at_flags)))? {
+                Ok(sub) => sub,
+                Err(Error::ENOENT) => continue,
+                Err(e) => return Err(e),
+            };
+            if Walk::Stop == walk_in(&sub, depth + 1, max_depth, at_flags, f)? { return Ok(Walk::Stop) }
+            if Walk::Stop == f(&WalkEntry { dir, entry: &entry, depth, post: true })? { return Ok(Walk::Stop) }
+        }
+    }
+    Ok(Walk::Continue)
+}
+
+/// Remove the file at `path`, and if it is a directory, all its contents, recursively.
+///
+/// Symbolic links are removed, not followed.
+/// Fails with [`ENOTEMPTY`](../struct.Error.html#associatedconstant.ENOTEMPTY) if the tree is deeper than `MAX_WALK_DEPTH`.
+pub fn remove_all_at(opt_dir: Option<&File>, path: &Str) -> Result<(), Error> {
+    let dir = match open_dir(opt_dir, path, nofollow(AtFlags::empty())) {
+        Ok(dir) => dir,
+        Err(Error::ENOTDIR) | Err(Error::ELOOP) => return unlink_at(opt_dir, path),
+        Err(e) => return Err(e),
+    };
+    walk_in(&dir, 0, MAX_WALK_DEPTH, AtFlags::empty(), &mut |we: &WalkEntry| {
+        match with_name_str(we.entry.name, |name| match (FileType::Directory == we.entry.typ, we.post) {
+            (false, _)    => unlink_at(Some(we.dir), name),
+            (true, true)  => rmdir_at(Some(we.dir), name),
+            (true, false) => Ok(()),
+        })? {
+            // Someone else removed it first.
+            Ok(()) | Err(Error::ENOENT) => Ok(Walk::Continue),
+            Err(e) => Err(e),
+        }
+    })?;
+    rmdir_at(opt_dir, path)
+}
+
+#[inline]
+fn nofollow(at_flags: AtFlags) -> usize {
+    libc::O_CLOEXEC as usize | if at_flags.contains(AtFlags::Follow) { 0 } else { libc::O_NOFOLLOW as usize }
+}
+
+/// Call `f` with `name` as a null-terminated string.
+#[inline]
+pub(crate) fn with_name_str<A, F: FnOnce(&Str) -> A>(name: &[u8], f: F) -> Result<A, Error> {
+    let mut buf = [0; 0x100];
+    if name.len() >= buf.len() { return Err(Error::ENAMETOOLONG) }
+    buf[..name.len()].copy_from_slice(name);
+    Ok(f(<&Str>::try_from(&buf[..name.len() + 1]).map_err(|()| Error::EINVAL)?))
+}
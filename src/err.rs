@@ -60,3 +60,23 @@ macro_rules! esyscall {
 /// return value.
 #[macro_export]
 macro_rules! esyscall_ { ($n:ident $(, $a:expr)*) => (esyscall!($n $(, $a)*).map(|_| ())) }
+
+/// Make a system call by number, for system calls too new to be in `syscall::nr`.
+///
+/// Works like [`esyscall`](macro.esyscall.html) otherwise.
+macro_rules! esyscall_nr {
+    ($n:expr) =>
+        ($crate::Error::from_sysret(::syscall::syscall0($n) as isize));
+    ($n:expr, $a1:expr) =>
+        ($crate::Error::from_sysret(::syscall::syscall1($n, $a1 as usize) as isize));
+    ($n:expr, $a1:expr, $a2:expr) =>
+        ($crate::Error::from_sysret(::syscall::syscall2($n, $a1 as usize, $a2 as usize) as isize));
+    ($n:expr, $a1:expr, $a2:expr, $a3:expr) =>
+        ($crate::Error::from_sysret(::syscall::syscall3($n, $a1 as usize, $a2 as usize, $a3 as usize) as isize));
+    ($n:expr, $a1:expr, $a2:expr, $a3:expr, $a4:expr) =>
+        ($crate::Error::from_sysret(::syscall::syscall4($n, $a1 as usize, $a2 as usize, $a3 as usize,
+                                                        $a4 as usize) as isize));
+    ($n:expr, $a1:expr, $a2:expr, $a3:expr, $a4:expr, $a5:expr) =>
+        ($crate::Error::from_sysret(::syscall::syscall5($n, $a1 as usize, $a2 as usize, $a3 as usize,
+                                                        $a4 as usize, $a5 as usize) as isize));
+}
@@ -21,6 +21,12 @@ use {Error, Str};
 use time::*;
 use util::*;
 
+#[cfg(target_os = "linux")]
+pub mod copy;
+pub mod lock;
+#[cfg(target_os = "linux")]
+pub mod xattr;
+
 /// File descriptor, closed on drop
 #[derive(Debug)]
 pub struct File {
@@ -34,6 +40,31 @@ impl File {
         unsafe { esyscall_!(FCHMOD, self.fd, mode.bits) }
     }
 
+    /// Change the owner and group of the file; `None` leaves either as it is.
+    #[inline]
+    pub fn chown(&self, uid: Option<libc::uid_t>, gid: Option<libc::gid_t>) -> Result<(), Error> {
+        unsafe { esyscall_!(FCHOWN, self.fd, opt_id(uid), opt_id(gid)) }
+    }
+
+    /// Set the times of last access and modification of the file.
+    ///
+    /// This passes a null path to `utimensat`, which only Linux allows.
+    #[cfg(target_os = "linux")]
+    #[inline]
+    pub fn set_times(&self, atime: SetTime, mtime: SetTime) -> Result<(), Error> {
+        let ts = [atime.to_c_timespec()?, mtime.to_c_timespec()?];
+        unsafe { esyscall_!(UTIMENSAT, self.fd, ::core::ptr::null::<u8>(), &ts as *const libc::timespec, 0) }
+    }
+
+    /// Return information about the filesystem containing the file.
+    #[cfg(target_os = "linux")]
+    #[inline]
+    pub fn statfs(&self) -> Result<FsStat, Error> { unsafe {
+        let mut st = MU::<statfs>::uninit();
+        esyscall!(FSTATFS, self.fd, st.as_mut_ptr())?;
+        Ok(FsStat::from(st.assume_init()))
+    } }
+
     /// Return information about the file.
     #[inline]
     pub fn stat(&self) -> Result<Stat, Error> { unsafe {
@@ -56,6 +87,77 @@ impl File {
         unsafe { esyscall_!(FTRUNCATE, self.fd, try_to_usize(length)?) }
     }
 
+    /// Allocate space for `len` bytes of the file from `offset`, or otherwise change that range as `flags` say.
+    ///
+    /// Fails with [`EOPNOTSUPP`](../struct.Error.html#associatedconstant.EOPNOTSUPP) if the filesystem does not support `flags`,
+    /// in which case the caller might rather write zeros.
+    #[cfg(target_os = "linux")]
+    #[inline]
+    pub fn allocate(&self, offset: u64, len: u64, flags: AllocFlags) -> Result<(), Error> {
+        unsafe { esyscall_!(FALLOCATE, self.fd, flags.bits, try_to_usize(offset)?, try_to_usize(len)?) }
+    }
+
+    /// Advise the system how the caller will access `len` bytes of the file from `offset`, or to the end of file if `len` is 0.
+    #[cfg(target_os = "linux")]
+    #[inline]
+    pub fn advise(&self, offset: u64, len: u64, advice: Advice) -> Result<(), Error> {
+        unsafe { esyscall_!(FADVISE64, self.fd, try_to_usize(offset)?, try_to_usize(len)?, advice as usize) }
+    }
+
+    /// Read from the file at `offset` into `buf`, and return how many bytes were read.
+    ///
+    /// The file offset is neither used nor changed.
+    #[inline]
+    pub fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize, Error> {
+        unsafe { esyscall!(PREAD64, self.fd, buf.as_mut_ptr(), buf.len(), try_to_usize(offset)?) }
+    }
+
+    /// Write `buf` to the file at `offset`, and return how many bytes were written.
+    ///
+    /// The file offset is neither used nor changed.
+    #[inline]
+    pub fn write_at(&self, buf: &[u8], offset: u64) -> Result<usize, Error> {
+        unsafe { esyscall!(PWRITE64, self.fd, buf.as_ptr(), buf.len(), try_to_usize(offset)?) }
+    }
+
+    /// Read from the file at `offset` into `bufs`, in order, and return how many bytes were read.
+    ///
+    /// The file offset is neither used nor changed.
+    #[cfg(target_os = "linux")]
+    #[inline]
+    pub fn readv_at(&self, bufs: &mut [&mut [u8]], offset: u64, flags: RwFlags) -> Result<usize, Error> {
+        unsafe { esyscall!(PREADV2, self.fd, bufs.as_mut_ptr(), bufs.len(),
+                           offset as usize, (offset >> 32) as usize, flags.bits) }
+    }
+
+    /// Write `bufs` to the file at `offset`, in order, and return how many bytes were written.
+    ///
+    /// The file offset is neither used nor changed, unless `flags` contains `Append`, in which case `offset` is ignored and the data are written at the end of the file.
+    #[cfg(target_os = "linux")]
+    #[inline]
+    pub fn writev_at(&self, bufs: &[&[u8]], offset: u64, flags: RwFlags) -> Result<usize, Error> {
+        unsafe { esyscall!(PWRITEV2, self.fd, bufs.as_ptr(), bufs.len(),
+                           offset as usize, (offset >> 32) as usize, flags.bits) }
+    }
+
+    /// Set the file offset as `whence` says, and return the new offset.
+    ///
+    /// Fails with [`EOVERFLOW`](../struct.Error.html#associatedconstant.EOVERFLOW) if the offset does not fit in a machine word.
+    #[inline]
+    pub fn seek_to(&self, whence: Whence) -> Result<u64, Error> {
+        let to_isize = |n: i64| isize::try_from(n).map(|n| n as usize).map_err(|_| Error::EOVERFLOW);
+        let (offset, w) = match whence {
+            Whence::Start(n) => (try_to_usize(n)?, libc::SEEK_SET),
+            Whence::End(n)   => (to_isize(n)?, libc::SEEK_END),
+            Whence::Here(n)  => (to_isize(n)?, libc::SEEK_CUR),
+            #[cfg(target_os = "linux")]
+            Whence::Data(n)  => (try_to_usize(n)?, libc::SEEK_DATA),
+            #[cfg(target_os = "linux")]
+            Whence::Hole(n)  => (try_to_usize(n)?, libc::SEEK_HOLE),
+        };
+        unsafe { esyscall!(LSEEK, self.fd, offset, w) }.map(|n| n as u64)
+    }
+
     /// Execute the program file.
     ///
     /// The current program of the calling process is replaced with the new one, with a fresh stack, heap, and data segment.
@@ -78,6 +180,89 @@ impl File {
         unsafe { esyscall!(FEXECVE, self.fd, argv as *const _, envp as *const _).map(|_| unreach()) }
     }
 
+    /// Return whether the close-on-exec flag is set on the file descriptor.
+    #[inline]
+    pub fn is_cloexec(&self) -> Result<bool, Error> {
+        unsafe { esyscall!(FCNTL, self.fd, libc::F_GETFD) }.map(|fl| 0 != fl & libc::FD_CLOEXEC as usize)
+    }
+
+    /// Set or clear the close-on-exec flag on the file descriptor.
+    #[inline]
+    pub fn set_cloexec(&self, cloexec: bool) -> Result<(), Error> { unsafe {
+        let fl = esyscall!(FCNTL, self.fd, libc::F_GETFD)? & !(libc::FD_CLOEXEC as usize);
+        esyscall_!(FCNTL, self.fd, libc::F_SETFD, fl | if cloexec { libc::FD_CLOEXEC as usize } else { 0 })
+    } }
+
+    /// Return whether the file is in non-blocking mode.
+    #[inline]
+    pub fn is_nonblocking(&self) -> Result<bool, Error> { self.status_flag(libc::O_NONBLOCK) }
+
+    /// Set or clear non-blocking mode on the file.
+    ///
+    /// This affects every descriptor of the open file description.
+    #[inline]
+    pub fn set_nonblocking(&self, nonblocking: bool) -> Result<(), Error> {
+        self.set_status_flag(libc::O_NONBLOCK, nonblocking)
+    }
+
+    /// Return whether the file is in append mode.
+    #[inline]
+    pub fn is_append(&self) -> Result<bool, Error> { self.status_flag(libc::O_APPEND) }
+
+    /// Set or clear append mode on the file: in it, every write is at the end of the file.
+    ///
+    /// This affects every descriptor of the open file description.
+    #[inline]
+    pub fn set_append(&self, append: bool) -> Result<(), Error> { self.set_status_flag(libc::O_APPEND, append) }
+
+    #[inline]
+    fn status_flag(&self, flag: libc::c_int) -> Result<bool, Error> {
+        unsafe { esyscall!(FCNTL, self.fd, libc::F_GETFL) }.map(|fl| 0 != fl & flag as usize)
+    }
+
+    #[inline]
+    fn set_status_flag(&self, flag: libc::c_int, set: bool) -> Result<(), Error> { unsafe {
+        let fl = esyscall!(FCNTL, self.fd, libc::F_GETFL)? & !(flag as usize);
+        esyscall_!(FCNTL, self.fd, libc::F_SETFL, fl | if set { flag as usize } else { 0 })
+    } }
+
+    /// Duplicate the file descriptor to the lowest free one at least `min_fd`, with the close-on-exec flag set.
+    #[inline]
+    pub fn dup_min(&self, min_fd: isize) -> Result<File, Error> {
+        unsafe { esyscall!(FCNTL, self.fd, libc::F_DUPFD_CLOEXEC, min_fd) }.map(|fd| File { fd: fd as _ })
+    }
+
+    /// Return the capacity of the pipe, in bytes.
+    #[cfg(target_os = "linux")]
+    #[inline]
+    pub fn pipe_size(&self) -> Result<usize, Error> {
+        unsafe { esyscall!(FCNTL, self.fd, libc::F_GETPIPE_SZ) }
+    }
+
+    /// Set the capacity of the pipe to at least `size` bytes, and return the capacity it was actually set to.
+    #[cfg(target_os = "linux")]
+    #[inline]
+    pub fn set_pipe_size(&self, size: usize) -> Result<usize, Error> {
+        unsafe { esyscall!(FCNTL, self.fd, libc::F_SETPIPE_SZ, size) }
+    }
+
+    /// Return the seals of the file.
+    #[cfg(target_os = "linux")]
+    #[inline]
+    pub fn seals(&self) -> Result<Seals, Error> {
+        unsafe { esyscall!(FCNTL, self.fd, libc::F_GET_SEALS) }.map(|s| Seals::from_bits_truncate(s as _))
+    }
+
+    /// Add `seals` to the seals of the file.
+    ///
+    /// Fails with [`EPERM`](../struct.Error.html#associatedconstant.EPERM) if the file has `Seals::Seal`,
+    /// and with [`EINVAL`](../struct.Error.html#associatedconstant.EINVAL) if it does not support sealing, e.g. it was not made by `memfd_create` with sealing allowed.
+    #[cfg(target_os = "linux")]
+    #[inline]
+    pub fn add_seals(&self, seals: Seals) -> Result<(), Error> {
+        unsafe { esyscall_!(FCNTL, self.fd, libc::F_ADD_SEALS, seals.bits) }
+    }
+
     /// Return the file descriptor of the `File`.
     #[inline]
     pub fn fd(&self) -> isize { self.fd }
@@ -119,6 +304,147 @@ pub fn open_at(opt_dir: Option<&File>, path: &Str, o_mode: OpenMode,
     } }.map(|fd| File { fd: fd as isize })
 }
 
+/// Open the file at the given path, like `open_at`, but resolving the path under the restrictions in `resolve`.
+///
+/// On systems without `openat2`, or which deny it, this resolves the path itself, a component at a time, with the same restrictions, except:
+/// `NoXDev` compares devices, so it does not notice crossing a bind mount of the same filesystem;
+/// `NoMagicLinks` refuses every symbolic link on procfs, as magic links can not be told from others there,
+/// and without it, magic links are never followed as such, but their targets are resolved as paths, like those of other links;
+/// and `Cached` always fails with [`EAGAIN`](../struct.Error.html#associatedconstant.EAGAIN).
+///
+/// With `Beneath` or `InRoot`, after each `..` the fallback checks that it is still under the starting directory,
+/// and fails with [`EAGAIN`](../struct.Error.html#associatedconstant.EAGAIN) if a concurrent rename moved it out, as `openat2` does.
+#[cfg(target_os = "linux")]
+pub fn open_at2(opt_dir: Option<&File>, path: &Str, o_mode: OpenMode,
+                f_mode: Option<Mode>, resolve: Resolve) -> Result<File, Error> {
+    let how = open_how {
+        flags: (o_mode.0 | if f_mode.is_some() { libc::O_CREAT as usize } else { 0 }) as u64,
+        mode: f_mode.map_or(0, |m| m.bits) as u64,
+        resolve: resolve.bits,
+    };
+    match unsafe { esyscall_nr!(::util::nr::OPENAT2, from_opt_dir(opt_dir), path.as_ptr(),
+                                &how as *const open_how, mem::size_of::<open_how>()) } {
+        Ok(fd) => Ok(File { fd: fd as _ }),
+        // A seccomp filter may deny system calls it does not know with `EPERM`.
+        Err(Error::ENOSYS) | Err(Error::EPERM) => open_at2_fallback(opt_dir, path, o_mode, f_mode, resolve),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn open_at2_fallback(opt_dir: Option<&File>, path: &Str, o_mode: OpenMode,
+                     f_mode: Option<Mode>, resolve: Resolve) -> Result<File, Error> {
+    use dir::with_name_str;
+
+    if resolve.is_empty() { return open_at(opt_dir, path, o_mode, f_mode) }
+    // We can not know what is cached, so fail as the system would if it were not, and the caller can retry without `Cached`.
+    if resolve.contains(Resolve::Cached) { return Err(Error::EAGAIN) }
+    let confined = resolve.intersects(Resolve::Beneath | Resolve::InRoot);
+
+    let root = open_path(opt_dir, str0!("."))?;
+    let root_st = root.stat()?;
+    let check_dev = |f: File| if resolve.contains(Resolve::NoXDev) && f.stat()?.dev != root_st.dev {
+        Err(Error::EXDEV)
+    } else { Ok(f) };
+    // Whether `dir`, `depth` levels down, has `root` as its ancestor there
+    let is_under_root = |dir: &File, depth: usize| -> Result<bool, Error> {
+        let mut f = open_path(Some(dir), str0!("."))?;
+        for _ in 0..depth { f = open_path(Some(&f), str0!(".."))? }
+        f.stat().map(|st| (st.dev, st.ino) == (root_st.dev, root_st.ino))
+    };
+
+    // The rest of the path is `buf[k..]`, at the end, so a link target can be put in front of it.
+    let mut buf = [0; 0x1000];
+    let path = &path[..];
+    if path.is_empty() { return Err(Error::ENOENT) }
+    if path.len() > buf.len() { return Err(Error::ENAMETOOLONG) }
+    let mut k = buf.len() - path.len();
+    buf[k..].copy_from_slice(path);
+
+    let mut link_buf = [0; 0x1000];
+    let mut links = 0;
+    let mut depth = 0usize;
+    let mut dir = root.try_clone()?;
+    loop {
+        if Some(&b'/') == buf.get(k) {
+            if resolve.contains(Resolve::Beneath) { return Err(Error::EXDEV) }
+            dir = if resolve.contains(Resolve::InRoot) { root.try_clone()? }
+                  else { check_dev(open_path(None, str0!("/"))?)? };
+            depth = 0;
+            while Some(&b'/') == buf.get(k) { k += 1 }
+        }
+
+        let e = buf[k..].iter().position(|&b| b'/' == b).map_or(buf.len(), |n| k + n);
+        let mut r = e;
+        while Some(&b'/') == buf.get(r) { r += 1 }
+        let last = buf.len() == r;
+        let name = &buf[k..e];
+
+        if name.is_empty() || b"." == name || b".." == name && confined && 0 == depth {
+            if b".." == name && resolve.contains(Resolve::Beneath) { return Err(Error::EXDEV) }
+            if last { return open_at(Some(&dir), str0!("."), o_mode, f_mode) }
+        } else if b".." == name {
+            dir = check_dev(open_path(Some(&dir), str0!(".."))?)?;
+            depth = depth.saturating_sub(1);
+            if confined && !is_under_root(&dir, depth)? { return Err(Error::EAGAIN) }
+            if last { return open_at(Some(&dir), str0!("."), o_mode, f_mode) }
+        } else {
+            let is_link = if last {
+                match with_name_str(name, |name| open_at(Some(&dir), name,
+                                                         OpenMode(o_mode.0 | libc::O_NOFOLLOW as usize), f_mode))? {
+                    Ok(f) => return check_dev(f),
+                    // The caller asked not to follow a link there.
+                    Err(Error::ELOOP) if 0 == o_mode.0 & libc::O_NOFOLLOW as usize => true,
+                    Err(e) => return Err(e),
+                }
+            } else {
+                let f = with_name_str(name, |name| open_path(Some(&dir), name))??;
+                match f.stat()?.typ {
+                    FileType::Symlink => true,
+                    FileType::Directory => { dir = check_dev(f)?; depth += 1; false },
+                    _ => return Err(Error::ENOTDIR),
+                }
+            };
+            if is_link {
+                links += 1;
+                if resolve.contains(Resolve::NoSymlinks) || links > 40 { return Err(Error::ELOOP) }
+                if resolve.contains(Resolve::NoMagicLinks) && FsType::Proc == dir.statfs()?.typ { return Err(Error::ELOOP) }
+                let n = match with_name_str(name, |name| readlink_at(Some(&dir), name, &mut link_buf).map(|t| t.len()))? {
+                    Ok(n) => n,
+                    // Not a link, so `O_NOFOLLOW` was not why it failed.
+                    Err(Error::EINVAL) => return Err(Error::ELOOP),
+                    Err(e) => return Err(e),
+                };
+                let target = &link_buf[..n];
+                let l = target.len() + if last { 0 } else { 1 };
+                if l > r { return Err(Error::ENAMETOOLONG) }
+                k = r - l;
+                buf[k..k + target.len()].copy_from_slice(target);
+                if !last { buf[r - 1] = b'/' }
+                continue;
+            }
+        }
+        k = r;
+    }
+}
+
+/// Open the file at `path` with `O_PATH`, not following a symbolic link there.
+#[cfg(target_os = "linux")]
+#[inline]
+fn open_path(opt_dir: Option<&File>, path: &Str) -> Result<File, Error> {
+    unsafe { esyscall!(OPENAT, from_opt_dir(opt_dir), path.as_ptr(),
+                       libc::O_PATH | libc::O_NOFOLLOW | libc::O_CLOEXEC) }
+        .map(|fd| File { fd: fd as _ })
+}
+
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct open_how {
+    flags: u64,
+    mode: u64,
+    resolve: u64,
+}
+
 /// Rename the file from `old_path` to `new_path`.
 #[inline]
 pub fn rename_at(opt_old_dir: Option<&File>, old_path: &Str,
@@ -127,6 +453,15 @@ pub fn rename_at(opt_old_dir: Option<&File>, old_path: &Str,
                                   from_opt_dir(opt_new_dir), new_path.as_ptr()) }
 }
 
+/// Rename the file from `old_path` to `new_path`, as `flags` say.
+#[cfg(target_os = "linux")]
+#[inline]
+pub fn rename_at2(opt_old_dir: Option<&File>, old_path: &Str,
+                  opt_new_dir: Option<&File>, new_path: &Str, flags: RenameFlags) -> Result<(), Error> {
+    unsafe { esyscall_!(RENAMEAT2, from_opt_dir(opt_old_dir), old_path.as_ptr(),
+                                   from_opt_dir(opt_new_dir), new_path.as_ptr(), flags.bits) }
+}
+
 /// Link the file from `old_path` to `new_path`.
 #[inline]
 pub fn link_at(opt_old_dir: Option<&File>, old_path: &Str,
@@ -146,6 +481,74 @@ pub fn unlink_at(opt_dir: Option<&File>, path: &Str) -> Result<(), Error> {
     unsafe { esyscall_!(UNLINKAT, from_opt_dir(opt_dir), path.as_ptr(), 0) }
 }
 
+/// Make a symbolic link at `path`, which refers to `target`.
+#[inline]
+pub fn symlink_at(target: &Str, opt_dir: Option<&File>, path: &Str) -> Result<(), Error> {
+    unsafe { esyscall_!(SYMLINKAT, target.as_ptr(), from_opt_dir(opt_dir), path.as_ptr()) }
+}
+
+/// Read the target of the symbolic link at `path` into `buf`, and return it, null-terminated.
+///
+/// Fails with [`ERANGE`](../struct.Error.html#associatedconstant.ERANGE) if `buf` is too small.
+#[inline]
+pub fn readlink_at<'a>(opt_dir: Option<&File>, path: &Str, buf: &'a mut [u8]) -> Result<&'a mut Str, Error> {
+    let n = unsafe { esyscall!(READLINKAT, from_opt_dir(opt_dir), path.as_ptr(), buf.as_mut_ptr(), buf.len())? };
+    if n >= buf.len() { return Err(Error::ERANGE) }
+    buf[n] = 0;
+    Ok(<&mut Str>::try_from(&mut buf[..n+1]).unwrap())
+}
+
+/// Read the target of the symbolic link at `path`, in a buffer which grows as need be.
+#[cfg(feature = "alloc")]
+pub fn readlink_at_vec(opt_dir: Option<&File>, path: &Str) -> Result<::alloc::vec::Vec<u8>, Error> {
+    let mut buf = ::alloc::vec::Vec::with_capacity(0x100);
+    loop {
+        let n = unsafe { esyscall!(READLINKAT, from_opt_dir(opt_dir), path.as_ptr(),
+                                   buf.as_mut_ptr(), buf.capacity())? };
+        if n < buf.capacity() {
+            unsafe { buf.set_len(n) };
+            return Ok(buf);
+        }
+        let c = buf.capacity();
+        buf.reserve_exact(2 * c);
+    }
+}
+
+/// Resolve `path` to a canonical absolute path, with no symbolic links nor `.` or `..` components, in `buf`, and return it, null-terminated.
+///
+/// If `at_flags` does not contain `Follow` and the file at `path` is a symbolic link, it is not itself resolved.
+/// This needs `/proc` to be mounted.
+#[cfg(target_os = "linux")]
+pub fn realpath_at<'a>(opt_dir: Option<&File>, path: &Str, at_flags: AtFlags,
+                       buf: &'a mut [u8]) -> Result<&'a mut Str, Error> {
+    let f = unsafe { esyscall!(OPENAT, from_opt_dir(opt_dir), path.as_ptr(),
+                               libc::O_PATH | libc::O_CLOEXEC |
+                               if at_flags.contains(AtFlags::Follow) { 0 } else { libc::O_NOFOLLOW }) }
+        .map(|fd| File { fd: fd as _ })?;
+    let mut fd_path_buf = [0; 0x20];
+    readlink_at(None, proc_fd_path(&f, &mut fd_path_buf), buf)
+}
+
+/// Return the path of `f` in `/proc/self/fd`.
+#[cfg(target_os = "linux")]
+pub(crate) fn proc_fd_path<'a>(f: &File, buf: &'a mut [u8; 0x20]) -> &'a Str {
+    const prefix: &[u8] = b"/proc/self/fd/";
+    buf[..prefix.len()].copy_from_slice(prefix);
+    let mut digits = [0; 0x14];
+    let mut n = f.fd as usize;
+    let mut k = digits.len();
+    loop {
+        k -= 1;
+        digits[k] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if 0 == n { break }
+    }
+    let l = prefix.len() + digits.len() - k;
+    buf[prefix.len()..l].copy_from_slice(&digits[k..]);
+    buf[l] = 0;
+    <&Str>::try_from(&buf[..l+1]).unwrap()
+}
+
 /// Change the mode of the file at `path`.
 #[inline]
 pub fn chmod_at(opt_dir: Option<&File>, path: &Str,
@@ -155,6 +558,96 @@ pub fn chmod_at(opt_dir: Option<&File>, path: &Str,
                         else { libc::AT_SYMLINK_NOFOLLOW }) }
 }
 
+/// Change the owner and group of the file at `path`; `None` leaves either as it is.
+#[inline]
+pub fn chown_at(opt_dir: Option<&File>, path: &Str, uid: Option<libc::uid_t>, gid: Option<libc::gid_t>,
+                at_flags: AtFlags) -> Result<(), Error> {
+    unsafe { esyscall_!(FCHOWNAT, from_opt_dir(opt_dir), path.as_ptr(), opt_id(uid), opt_id(gid),
+                        if at_flags.contains(AtFlags::Follow) { 0 }
+                        else { libc::AT_SYMLINK_NOFOLLOW }) }
+}
+
+#[inline]
+fn opt_id(opt_id: Option<u32>) -> usize { opt_id.map_or(!0 as u32, |id| id) as usize }
+
+/// Set the times of last access and modification of the file at `path`.
+#[inline]
+pub fn set_times_at(opt_dir: Option<&File>, path: &Str, atime: SetTime, mtime: SetTime,
+                    at_flags: AtFlags) -> Result<(), Error> {
+    let ts = [atime.to_c_timespec()?, mtime.to_c_timespec()?];
+    unsafe { esyscall_!(UTIMENSAT, from_opt_dir(opt_dir), path.as_ptr(), &ts as *const libc::timespec,
+                        if at_flags.contains(AtFlags::Follow) { 0 }
+                        else { libc::AT_SYMLINK_NOFOLLOW }) }
+}
+
+/// Time to set as the time of last access or modification of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SetTime {
+    /** The present time      */ Now,
+    /** Leave it as it is     */ Omit,
+    /** The given time        */ At(EpochTime),
+}
+
+impl SetTime {
+    #[inline]
+    fn to_c_timespec(self) -> Result<libc::timespec, Error> {
+        let nsec = |tv_nsec| libc::timespec { tv_sec: 0, tv_nsec };
+        match self {
+            SetTime::Now => Ok(nsec(libc::UTIME_NOW as _)),
+            SetTime::Omit => Ok(nsec(libc::UTIME_OMIT as _)),
+            SetTime::At(t) => t.to_c_timespec().ok_or(Error::EOVERFLOW),
+        }
+    }
+}
+
+/// Check whether the caller may access the file at `path` as `perm` says, or, if `perm` is empty, whether the file is at all.
+///
+/// This checks as the system would on access, including ACLs, supplementary groups, and read-only mounts,
+/// as the real user and group IDs unless `flags` contains `Effective`.
+/// On systems without `faccessat2`, or which deny it, this fails with [`ENOSYS`](../struct.Error.html#associatedconstant.ENOSYS)
+/// if `flags` would matter: i.e. contains `Effective` and those IDs differ from the real ones, or does not contain `Follow` and the file is a symbolic link.
+#[cfg(target_os = "linux")]
+pub fn access_at(opt_dir: Option<&File>, path: &Str, perm: Permission, flags: AccessFlags) -> Result<(), Error> {
+    let fl = if flags.contains(AccessFlags::Follow) { 0 } else { libc::AT_SYMLINK_NOFOLLOW as usize }
+           | (flags & AccessFlags::Effective).bits;
+    match unsafe { esyscall_nr!(::util::nr::FACCESSAT2, from_opt_dir(opt_dir), path.as_ptr(), perm.bits(), fl) } {
+        Ok(_) => return Ok(()),
+        // A seccomp filter may deny system calls it does not know with `EPERM`.
+        Err(Error::ENOSYS) | Err(Error::EPERM) => (),
+        Err(e) => return Err(e),
+    }
+    if flags.contains(AccessFlags::Effective) && unsafe {
+        syscall!(GETUID) != syscall!(GETEUID) || syscall!(GETGID) != syscall!(GETEGID)
+    } { return Err(Error::ENOSYS) }
+    if !flags.contains(AccessFlags::Follow) && FileType::Symlink == stat_at(opt_dir, path, AtFlags::empty())?.typ {
+        return Err(Error::ENOSYS)
+    }
+    unsafe { esyscall_!(FACCESSAT, from_opt_dir(opt_dir), path.as_ptr(), perm.bits()) }
+}
+
+/// Make a file of type `typ` at `path`, which is the device `dev` if `typ` is `CharDevice` or `BlockDevice`.
+///
+/// Fails with [`EINVAL`](../struct.Error.html#associatedconstant.EINVAL) if `typ` is `Directory`, `Symlink` or `Unknown`;
+/// rather use `dir::mkdir_at` or `symlink_at`.
+#[inline]
+pub fn mknod_at(opt_dir: Option<&File>, path: &Str, typ: FileType, mode: Mode, dev: Device) -> Result<(), Error> {
+    let typ = match typ {
+        FileType::Regular     => libc::S_IFREG,
+        FileType::CharDevice  => libc::S_IFCHR,
+        FileType::BlockDevice => libc::S_IFBLK,
+        FileType::Fifo        => libc::S_IFIFO,
+        FileType::Socket      => libc::S_IFSOCK,
+        FileType::Directory | FileType::Symlink | FileType::Unknown => return Err(Error::EINVAL),
+    };
+    unsafe { esyscall_!(MKNODAT, from_opt_dir(opt_dir), path.as_ptr(), typ as usize | mode.bits as usize, dev.0) }
+}
+
+/// Make a named pipe at `path`.
+#[inline]
+pub fn mkfifo_at(opt_dir: Option<&File>, path: &Str, mode: Mode) -> Result<(), Error> {
+    mknod_at(opt_dir, path, FileType::Fifo, mode, Device(0))
+}
+
 /// Return information about the file at `path`.
 #[inline]
 pub fn stat_at(opt_dir: Option<&File>, path: &Str,
@@ -172,6 +665,33 @@ pub fn stat_at(opt_dir: Option<&File>, path: &Str,
     Ok(Stat::from(st.assume_init()))
 } }
 
+/// Return extended information about the file at `path`, including which fields are meaningful.
+///
+/// `mask` says which fields the caller wants, but the system may return more or fewer; check `Statx::mask`.
+/// On systems without `statx`, or which deny it, this falls back to `stat_at`, and the fields it does not return, e.g. `btime`, are not in `Statx::mask`.
+#[cfg(target_os = "linux")]
+pub fn statx_at(opt_dir: Option<&File>, path: &Str,
+                at_flags: AtFlags, mask: StatxMask) -> Result<Statx, Error> {
+    let fl = if at_flags.contains(AtFlags::Follow) { 0 } else { libc::AT_SYMLINK_NOFOLLOW }
+           | AT_EMPTY_PATH;
+    let mut stx = MU::<statx>::uninit();
+    match unsafe { esyscall_!(STATX, from_opt_dir(opt_dir), path.as_ptr(), fl, mask.bits(), stx.as_mut_ptr()) } {
+        Ok(()) => Ok(Statx::from(unsafe { stx.assume_init() })),
+        // A seccomp filter may deny system calls it does not know with `EPERM`.
+        Err(Error::ENOSYS) | Err(Error::EPERM) => stat_at(opt_dir, path, at_flags).map(Statx::from),
+        Err(e) => Err(e),
+    }
+}
+
+/// Return information about the filesystem containing the file at `path`.
+#[cfg(target_os = "linux")]
+#[inline]
+pub fn statfs_at(opt_dir: Option<&File>, path: &Str, at_flags: AtFlags) -> Result<FsStat, Error> {
+    unsafe { esyscall!(OPENAT, from_opt_dir(opt_dir), path.as_ptr(),
+                       libc::O_PATH | libc::O_CLOEXEC | if at_flags.contains(AtFlags::Follow) { 0 } else { libc::O_NOFOLLOW }) }
+        .map(|fd| File { fd: fd as _ })?.statfs()
+}
+
 /// Execute the program file at `path`.
 ///
 /// The current program of the calling process is replaced with the new one, with a fresh stack, heap, and data segment.
@@ -239,14 +759,78 @@ pub enum Clobber {
 
 pub use self::Clobber::*;
 
+/// How durable to make an atomic write
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Durability {
+    /** Leave flushing to the system, so a crash may lose the new file, or the replacement. */ Volatile,
+    /** Flush the new file before it replaces the old, and the directory after.             */ Durable,
+}
+
 /// Atomically write the file at the given `path`:
 ///
 /// call the given `writer`, and only once it finishes (and not fails), atomically replace the file with a newly-written one.
+///
+/// Where the filesystem supports it, the new file is a `TempFile`, so it has no name until it is complete, and is never left behind;
+/// elsewhere, it has a random name in `opt_dir` until it is complete, and that name is unlinked if the `writer` fails.
+#[inline]
 pub fn atomic_write_file_at<F: FnOnce(File) -> Result<T, Error>, T>
   (opt_dir: Option<&File>, path: &Str,
    clobber: Clobber, mode: Mode, writer: F) -> Result<T, Error> {
-    let mut rng = ::rand::rngs::SmallRng::from_rng(::random::OsRandom::new())
-        .map_err(|_| Error::EIO)?;
+    atomic_write_file_at_with(opt_dir, path, clobber, mode, Durability::Volatile, writer)
+}
+
+/// Atomically write the file at the given `path`, like `atomic_write_file_at`, as durably as `durability` says.
+///
+/// With `ClobberSavingPerms`, the new file also gets the owner, group, and extended attributes, which include any ACLs, of the old,
+/// as far as the caller may give them.
+pub fn atomic_write_file_at_with<F: FnOnce(File) -> Result<T, Error>, T>
+  (opt_dir: Option<&File>, path: &Str,
+   clobber: Clobber, mode: Mode, durability: Durability, writer: F) -> Result<T, Error> {
+    let old = match clobber {
+        NoClobber | Clobber => None,
+        ClobberSavingPerms => match stat_at(opt_dir, path, AtFlags::empty()) {
+            Ok(st) => Some(st),
+            Err(Error::ENOENT) => None,
+            Err(e) => return Err(e),
+        },
+    };
+    let mode = old.map_or(mode, |st| st.mode);
+    let set_perms = |f: &File| {
+        if let Some(st) = old {
+            // Changing the owner may clear the set-ID bits, so do it before `chmod`.
+            match f.chown(Some(st.uid), Some(st.gid)) {
+                Ok(()) | Err(Error::EPERM) => (),
+                Err(e) => return Err(e),
+            }
+            #[cfg(target_os = "linux")]
+            copy_xattrs_at(opt_dir, path, f)?;
+        }
+        f.chmod(mode)
+    };
+    let sync = |f: &File| match durability {
+        Durability::Volatile => Ok(()),
+        // The mode, owner, and size must reach the device too, not only the data.
+        Durability::Durable => f.sync(true),
+    };
+
+    #[cfg(target_os = "linux")]
+    match TempFile::new_at(opt_dir, mode, OpenFlags::empty()) {
+        Ok(tf) => {
+            set_perms(tf.file())?;
+            let m = writer(tf.file().try_clone()?)?;
+            sync(tf.file())?;
+            match clobber {
+                NoClobber => tf.link_at(opt_dir, path)?,
+                Clobber | ClobberSavingPerms => tf.replace_at(opt_dir, path)?,
+            };
+            if Durability::Durable == durability { sync_parent_at(opt_dir, path)? }
+            return Ok(m);
+        },
+        Err(Error::EOPNOTSUPP) | Err(Error::EISDIR) | Err(Error::EINVAL) => (),
+        Err(e) => return Err(e),
+    }
+
+    let mut rng = new_rng()?;
 
     let mut temp_path = [b' '; 13];
     temp_path[temp_path.len() - 1] = 0;
@@ -260,27 +844,148 @@ pub fn atomic_write_file_at<F: FnOnce(File) -> Result<T, Error>, T>
     }
     let rm = Rm { opt_dir, path: temp_path_ref };
 
-    f.chmod(match clobber {
-        NoClobber | Clobber => mode,
-        ClobberSavingPerms => match stat_at(opt_dir, path, AtFlags::empty()) {
-            Ok(st) => st.mode,
-            Err(Error::ENOENT) => mode,
-            Err(e) => return Err(e),
-        },
-    })?;
+    set_perms(&f)?;
 
-    let m = writer(f)?;
+    let m = writer(f.try_clone()?)?;
+    sync(&f)?;
 
     match clobber {
+        #[cfg(target_os = "linux")]
+        NoClobber => match rename_at2(opt_dir, temp_path_ref, opt_dir, path, RenameFlags::NoReplace) {
+            Ok(()) => mem::forget(rm),
+            // The filesystem may not support `NoReplace`.
+            Err(Error::EINVAL) | Err(Error::ENOSYS) => link_at(opt_dir, temp_path_ref, opt_dir, path, AtFlags::empty())?,
+            Err(e) => return Err(e),
+        },
+        #[cfg(not(target_os = "linux"))]
         NoClobber => link_at(opt_dir, temp_path_ref, opt_dir, path, AtFlags::empty())?,
         Clobber | ClobberSavingPerms => {
             rename_at(opt_dir, temp_path_ref, opt_dir, path)?;
             mem::forget(rm);
         },
     }
+    if Durability::Durable == durability { sync_parent_at(opt_dir, path)? }
     Ok(m)
 }
 
+/// Flush the directory containing `path` to the device, so a change of what is linked at `path` is durable.
+fn sync_parent_at(opt_dir: Option<&File>, path: &Str) -> Result<(), Error> {
+    let path = &path[..];
+    let parent = match path.iter().rposition(|&b| b'/' == b) {
+        None => &b"."[..],
+        Some(0) => &b"/"[..],
+        Some(k) => &path[..k],
+    };
+    let mut buf = [0; 0x1000];
+    if parent.len() >= buf.len() { return Err(Error::ENAMETOOLONG) }
+    buf[..parent.len()].copy_from_slice(parent);
+    let parent = <&Str>::try_from(&buf[..parent.len() + 1]).map_err(|()| Error::EINVAL)?;
+    ::dir::open_at(opt_dir, parent, O_CLOEXEC)?.sync(true)
+}
+
+/// Copy the extended attributes of the file at `path` to `dst`, as far as the caller may.
+#[cfg(target_os = "linux")]
+fn copy_xattrs_at(opt_dir: Option<&File>, path: &Str, dst: &File) -> Result<(), Error> {
+    // Without `alloc`, this fails with `ERANGE` if the list of names or a value is too large for the buffer.
+    #[cfg(not(feature = "alloc"))]
+    let r = copy_xattrs_in(opt_dir, path, dst, &mut [0; 0x2000]);
+    // Grow the buffer until it can hold the largest list of names and value, each 64 KiB.
+    #[cfg(feature = "alloc")]
+    let r = {
+        let mut buf = ::alloc::vec::Vec::new();
+        buf.resize(0x2000, 0);
+        loop {
+            match copy_xattrs_in(opt_dir, path, dst, &mut buf) {
+                Err(Error::ERANGE) if buf.len() < 0x20000 => { let l = buf.len(); buf.resize(2 * l, 0) },
+                r => break r,
+            }
+        }
+    };
+    r
+}
+
+/// Copy the extended attributes as `copy_xattrs_at` does, with half of `buf` for the list of names and half for a value.
+#[cfg(target_os = "linux")]
+fn copy_xattrs_in(opt_dir: Option<&File>, path: &Str, dst: &File, buf: &mut [u8]) -> Result<(), Error> {
+    let (names, value) = buf.split_at_mut(buf.len() / 2);
+    let n = match xattr::list_at(opt_dir, path, names, AtFlags::empty()) {
+        Ok(n) => n,
+        Err(Error::EOPNOTSUPP) => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    for name in xattr::Names::new(&names[..n]) {
+        let l = match xattr::get_at(opt_dir, path, name, value, AtFlags::empty()) {
+            Ok(l) => l,
+            Err(Error::ENODATA) => continue,
+            Err(e) => return Err(e),
+        };
+        match dst.set_xattr(name, &value[..l], xattr::SetFlags::empty()) {
+            Ok(()) | Err(Error::EPERM) | Err(Error::EACCES) | Err(Error::EOPNOTSUPP) => (),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+#[inline]
+fn new_rng() -> Result<::rand::rngs::SmallRng, Error> {
+    ::rand::rngs::SmallRng::from_rng(::random::OsRandom::new()).map_err(|_| Error::EIO)
+}
+
+/// Unnamed temporary file, which is deleted when closed unless it was linked into the filesystem first
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+pub struct TempFile {
+    file: File,
+}
+
+#[cfg(target_os = "linux")]
+impl TempFile {
+    /// Make an unnamed regular file on the filesystem of `opt_dir`, opened for reading and writing.
+    ///
+    /// Fails with [`EOPNOTSUPP`](../struct.Error.html#associatedconstant.EOPNOTSUPP) if the filesystem does not support unnamed files,
+    /// or [`EISDIR`](../struct.Error.html#associatedconstant.EISDIR) if the system does not.
+    #[inline]
+    pub fn new_at(opt_dir: Option<&File>, mode: Mode, flags: OpenFlags) -> Result<Self, Error> {
+        OpenOptions::new().read(true).write(true).flags(O_TMPFILE | O_CLOEXEC | flags).create(Some(mode))
+            .open_at(opt_dir, str0!(".")).map(|file| TempFile { file })
+    }
+
+    /// Return the file.
+    #[inline]
+    pub fn file(&self) -> &File { &self.file }
+
+    /// Link the file at `path`, which must not already be, and return it.
+    #[inline]
+    pub fn link_at(self, opt_dir: Option<&File>, path: &Str) -> Result<File, Error> {
+        self.link(opt_dir, path).map(|()| self.file)
+    }
+
+    /// Link the file at `path`, atomically replacing any file already there, and return it.
+    ///
+    /// The file is briefly linked at a random name in `opt_dir` too.
+    pub fn replace_at(self, opt_dir: Option<&File>, path: &Str) -> Result<File, Error> {
+        let mut rng = new_rng()?;
+        let mut temp_path = [b' '; 13];
+        temp_path[temp_path.len() - 1] = 0;
+        let temp_path = <&mut Str>::try_from(&mut temp_path[..]).unwrap();
+        mktemp_helper(|temp_path| self.link(opt_dir, temp_path), temp_path, 0..12, &mut rng)?;
+        match rename_at(opt_dir, temp_path, opt_dir, path) {
+            Ok(()) => Ok(self.file),
+            Err(e) => { unlink_at(opt_dir, temp_path).unwrap_or(()); Err(e) },
+        }
+    }
+
+    fn link(&self, opt_dir: Option<&File>, path: &Str) -> Result<(), Error> {
+        // Linking by `AT_EMPTY_PATH` needs `CAP_DAC_READ_SEARCH`, but linking through `/proc` does not, so try that first.
+        let mut fd_path_buf = [0; 0x20];
+        match link_at(None, proc_fd_path(&self.file, &mut fd_path_buf), opt_dir, path, AtFlags::Follow) {
+            Err(Error::ENOENT) => link_at(Some(&self.file), str0!(""), opt_dir, path, AtFlags::empty()),
+            r => r,
+        }
+    }
+}
+
 impl TryClone for File {
     type Error = Error;
     #[inline]
@@ -319,6 +1024,45 @@ impl Write<u8> for File {
     fn flush(&mut self) -> Result<(), Self::Err> { self.sync(false) }
 }
 
+impl PosRead<u8> for File {
+    #[inline]
+    fn pread(&mut self, buf: &mut [u8], pos: usize) -> Result<usize, Self::Err> {
+        self.read_at(buf, pos as u64)
+    }
+
+    #[cfg(target_os = "linux")]
+    #[inline]
+    fn preadv(&mut self, bufs: &mut [&mut [u8]], pos: usize) -> Result<usize, Self::Err> {
+        self.readv_at(bufs, pos as u64, RwFlags::empty())
+    }
+}
+
+impl PosWrite<u8> for File {
+    #[inline]
+    fn pwrite(&mut self, buf: &[u8], pos: usize) -> Result<usize, Self::Err> {
+        self.write_at(buf, pos as u64)
+    }
+
+    #[cfg(target_os = "linux")]
+    #[inline]
+    fn pwritev(&mut self, bufs: &[&[u8]], pos: usize) -> Result<usize, Self::Err> {
+        self.writev_at(bufs, pos as u64, RwFlags::empty())
+    }
+}
+
+impl Seek for File {
+    type Err = Error;
+
+    #[inline]
+    fn seek(&mut self, from: SeekFrom) -> Result<u64, Error> {
+        self.seek_to(match from {
+            SeekFrom::Start(n) => Whence::Start(n),
+            SeekFrom::End(n)   => Whence::End(n),
+            SeekFrom::Here(n)  => Whence::Here(n),
+        })
+    }
+}
+
 impl fmt::Write for File {
     #[inline]
     fn write_str(&mut self, s: &str) -> fmt::Result {
@@ -434,38 +1178,322 @@ bitflags! {
     /// File creation and status flags
     pub struct OpenFlags: usize {
         /// Set the close-on-exec flag on the returned file descriptor.
-        const O_CLOEXEC  = libc::O_CLOEXEC  as usize;
+        const O_CLOEXEC   = libc::O_CLOEXEC   as usize;
         /// Ensure the call creates the file; if the file already is, the system call fails with
         /// [`EEXIST`](../struct.Error.html#associatedconstant.EEXIST).
-        const O_EXCL     = libc::O_EXCL     as usize;
+        const O_EXCL      = libc::O_EXCL      as usize;
         /// Open the file in non-blocking mode: operations which would block rather return
         /// [`EAGAIN`](../struct.Error.html#associatedconstant.EAGAIN) or
         /// [`EWOULDBLOCK`](../struct.Error.html#associatedconstant.EWOULDBLOCK).
-        const O_NONBLOCK = libc::O_NONBLOCK as usize;
+        const O_NONBLOCK  = libc::O_NONBLOCK  as usize;
         /// The file will not become the caller's controlling TTY, even if the file is a TTY and the process has none already.
-        const O_NOCTTY   = libc::O_NOCTTY   as usize;
+        const O_NOCTTY    = libc::O_NOCTTY    as usize;
+        /// Write at the end of the file, whatever the file offset.
+        const O_APPEND    = libc::O_APPEND    as usize;
+        /// Truncate the file to length 0, if it is a regular file opened for writing.
+        const O_TRUNC     = libc::O_TRUNC     as usize;
+        /// Fail with [`ENOTDIR`](../struct.Error.html#associatedconstant.ENOTDIR) if the file is not a directory.
+        const O_DIRECTORY = libc::O_DIRECTORY as usize;
+        /// Fail with [`ELOOP`](../struct.Error.html#associatedconstant.ELOOP) if the file is a symbolic link.
+        const O_NOFOLLOW  = libc::O_NOFOLLOW  as usize;
+        /// Open only a location in the filesystem, on which no I/O can be done, but which can be used as `opt_dir` and the like.
+        #[cfg(target_os = "linux")]
+        const O_PATH      = libc::O_PATH      as usize;
+        /// Make an unnamed regular file in the directory at the path, which can later be linked into the filesystem by `link_at`
+        /// unless `O_EXCL` is also given.
+        #[cfg(target_os = "linux")]
+        const O_TMPFILE   = libc::O_TMPFILE   as usize;
+        /// Do I/O directly to and from the buffers given, not through the cache, if possible.
+        #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "dragonfly", target_os = "netbsd"))]
+        const O_DIRECT    = libc::O_DIRECT    as usize;
+        /// Flush data and metadata to the device before each write returns, as `sync(true)` would.
+        const O_SYNC      = libc::O_SYNC      as usize;
+        /// Flush data, and metadata needed to read them, to the device before each write returns, as `sync(false)` would.
+        #[cfg(not(any(target_os = "freebsd", target_os = "dragonfly")))]
+        const O_DSYNC     = libc::O_DSYNC     as usize;
+        /// Do not update the time of last access when reading the file.
+        #[cfg(target_os = "linux")]
+        const O_NOATIME   = libc::O_NOATIME   as usize;
+    }
+}
+#[allow(missing_docs)] pub const O_CLOEXEC  : OpenFlags = OpenFlags::O_CLOEXEC;
+#[allow(missing_docs)] pub const O_EXCL     : OpenFlags = OpenFlags::O_EXCL;
+#[allow(missing_docs)] pub const O_NONBLOCK : OpenFlags = OpenFlags::O_NONBLOCK;
+#[allow(missing_docs)] pub const O_NOCTTY   : OpenFlags = OpenFlags::O_NOCTTY;
+#[allow(missing_docs)] pub const O_APPEND   : OpenFlags = OpenFlags::O_APPEND;
+#[allow(missing_docs)] pub const O_TRUNC    : OpenFlags = OpenFlags::O_TRUNC;
+#[allow(missing_docs)] pub const O_DIRECTORY: OpenFlags = OpenFlags::O_DIRECTORY;
+#[allow(missing_docs)] pub const O_NOFOLLOW : OpenFlags = OpenFlags::O_NOFOLLOW;
+#[cfg(target_os = "linux")]
+#[allow(missing_docs)] pub const O_PATH     : OpenFlags = OpenFlags::O_PATH;
+#[cfg(target_os = "linux")]
+#[allow(missing_docs)] pub const O_TMPFILE  : OpenFlags = OpenFlags::O_TMPFILE;
+#[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "dragonfly", target_os = "netbsd"))]
+#[allow(missing_docs)] pub const O_DIRECT   : OpenFlags = OpenFlags::O_DIRECT;
+#[allow(missing_docs)] pub const O_SYNC     : OpenFlags = OpenFlags::O_SYNC;
+#[cfg(not(any(target_os = "freebsd", target_os = "dragonfly")))]
+#[allow(missing_docs)] pub const O_DSYNC    : OpenFlags = OpenFlags::O_DSYNC;
+#[cfg(target_os = "linux")]
+#[allow(missing_docs)] pub const O_NOATIME  : OpenFlags = OpenFlags::O_NOATIME;
+
+/// Options to open a file with, which are checked for consistency before the system call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OpenOptions {
+    read: bool,
+    write: bool,
+    flags: OpenFlags,
+    create: Option<Mode>,
+}
+
+impl Default for OpenOptions {
+    #[inline]
+    fn default() -> Self { Self::new() }
+}
+
+impl OpenOptions {
+    /// Make options to open a file for neither reading nor writing, with no flags, and not to create it.
+    #[inline]
+    pub const fn new() -> Self { OpenOptions { read: false, write: false, flags: OpenFlags::empty(), create: None } }
+
+    /// Open the file for reading.
+    #[inline]
+    pub fn read(&mut self, read: bool) -> &mut Self { self.read = read; self }
+
+    /// Open the file for writing.
+    #[inline]
+    pub fn write(&mut self, write: bool) -> &mut Self { self.write = write; self }
+
+    /// Open the file with `flags`, in addition to any given before.
+    #[inline]
+    pub fn flags(&mut self, flags: OpenFlags) -> &mut Self { self.flags |= flags; self }
+
+    /// Create the file with the given mode if it is not already there.
+    ///
+    /// With `O_TMPFILE`, this gives the mode of the new file, and is needed.
+    #[inline]
+    pub fn create(&mut self, mode: Option<Mode>) -> &mut Self { self.create = mode; self }
+
+    /// Return the mode to open the file in, or fail with [`EINVAL`](../struct.Error.html#associatedconstant.EINVAL) if the options are inconsistent.
+    pub fn open_mode(&self) -> Result<OpenMode, Error> {
+        let &OpenOptions { read, write, flags, create } = self;
+        #[cfg(target_os = "linux")]
+        let (path, tmpfile) = (flags.contains(O_PATH), flags.contains(O_TMPFILE));
+        #[cfg(not(target_os = "linux"))]
+        let (path, tmpfile) = (false, false);
+
+        let ok = if path {
+            !read && !write && create.is_none() && (O_CLOEXEC | O_DIRECTORY | O_NOFOLLOW).contains(flags - O_PATH)
+        } else if tmpfile {
+            write && create.is_some()
+        } else {
+            (read || write) &&
+            (write || !flags.intersects(O_APPEND | O_TRUNC)) &&
+            (create.is_some() || !flags.contains(O_EXCL)) &&
+            (create.is_none() || !flags.contains(O_DIRECTORY))
+        };
+        if !ok { return Err(Error::EINVAL) }
+
+        Ok(match (read, write) {
+            (true, true)  => OpenMode::RdWr,
+            (false, true) => OpenMode::WrOnly,
+            _             => OpenMode::RdOnly,
+        } | flags)
+    }
+
+    /// Open the file at the given path, checking first that the options are consistent.
+    pub fn open_at(&self, opt_dir: Option<&File>, path: &Str) -> Result<File, Error> {
+        let o_mode = self.open_mode()?;
+        #[cfg(target_os = "linux")]
+        { if self.flags.contains(O_TMPFILE) {
+            // `O_TMPFILE` takes a mode, but fails with `O_CREAT`.
+            return unsafe { esyscall!(OPENAT, from_opt_dir(opt_dir), path.as_ptr(), o_mode.0,
+                                      self.create.map_or(0, |m| m.bits)) }.map(|fd| File { fd: fd as _ })
+        } }
+        open_at(opt_dir, path, o_mode, self.create)
     }
 }
-#[allow(missing_docs)] pub const O_CLOEXEC : OpenFlags = OpenFlags::O_CLOEXEC;
-#[allow(missing_docs)] pub const O_EXCL    : OpenFlags = OpenFlags::O_EXCL;
-#[allow(missing_docs)] pub const O_NONBLOCK: OpenFlags = OpenFlags::O_NONBLOCK;
-#[allow(missing_docs)] pub const O_NOCTTY  : OpenFlags = OpenFlags::O_NOCTTY;
 
 bitflags! {
     /// Flags modifying behavior of file operations
     pub struct AtFlags: usize {
         /// Follow symbolic links
-        const Follow = libc::AT_SYMLINK_FOLLOW as usize;
+        const Follow    = libc::AT_SYMLINK_FOLLOW as usize;
+    }
+}
+
+#[cfg(target_os = "linux")]
+bitflags! {
+    /// Flags modifying behavior of `access_at`
+    pub struct AccessFlags: usize {
+        /// Follow a symbolic link at the path.
+        const Follow    = libc::AT_SYMLINK_FOLLOW as usize;
+        /// Check access as the effective user and group IDs, rather than the real ones.
+        const Effective = AT_EACCESS as usize;
+    }
+}
+
+#[cfg(target_os = "linux")]
+bitflags! {
+    /// Restrictions on how `open_at2` resolves a path
+    pub struct Resolve: u64 {
+        /// Fail with [`EXDEV`](../struct.Error.html#associatedconstant.EXDEV) rather than cross a mount point.
+        const NoXDev       = 0x01;
+        /// Fail with [`ELOOP`](../struct.Error.html#associatedconstant.ELOOP) rather than follow a magic link, e.g. in `/proc/self/fd`.
+        const NoMagicLinks = 0x02;
+        /// Fail with [`ELOOP`](../struct.Error.html#associatedconstant.ELOOP) rather than follow any symbolic link.
+        const NoSymlinks   = 0x04;
+        /// Fail with [`EXDEV`](../struct.Error.html#associatedconstant.EXDEV) rather than leave the starting directory,
+        /// by `..`, an absolute path, or a symbolic link.
+        const Beneath      = 0x08;
+        /// Treat the starting directory as the root: `..` there stays there, and absolute paths and link targets are resolved against it.
+        const InRoot       = 0x10;
+        /// Resolve the path only from the cache, failing with [`EAGAIN`](../struct.Error.html#associatedconstant.EAGAIN) if that is not enough.
+        const Cached       = 0x20;
+    }
+}
+
+#[cfg(target_os = "linux")]
+bitflags! {
+    /// Flags modifying behavior of `rename_at2`
+    pub struct RenameFlags: usize {
+        /// Fail with [`EEXIST`](../struct.Error.html#associatedconstant.EEXIST) rather than replace a file at the new path.
+        const NoReplace = libc::RENAME_NOREPLACE as usize;
+        /// Atomically exchange the files at the old and new paths, which must both be.
+        const Exchange  = libc::RENAME_EXCHANGE  as usize;
+        /// Leave a whiteout at the old path, for overlay filesystems.
+        const Whiteout  = libc::RENAME_WHITEOUT  as usize;
+    }
+}
+
+#[cfg(target_os = "linux")]
+bitflags! {
+    /// Flags modifying behavior of `readv_at` and `writev_at`
+    pub struct RwFlags: usize {
+        /// Poll for completion, for low latency on fast devices.
+        const HiPri  = 0x01;
+        /// Write the data through to the device, as `sync(false)` would after.
+        const DSync  = 0x02;
+        /// Write the data and metadata through to the device, as `sync(true)` would after.
+        const Sync   = 0x04;
+        /// Fail with [`EAGAIN`](../struct.Error.html#associatedconstant.EAGAIN) rather than block, e.g. for data not in the cache.
+        const NoWait = 0x08;
+        /// Write at the end of the file, ignoring the offset.
+        const Append = 0x10;
+    }
+}
+
+#[cfg(target_os = "linux")]
+bitflags! {
+    /// Seals of a file, each of which forbids some modification of it
+    pub struct Seals: u32 {
+        /// Forbid adding further seals.
+        const Seal        = libc::F_SEAL_SEAL   as u32;
+        /// Forbid shrinking the file.
+        const Shrink      = libc::F_SEAL_SHRINK as u32;
+        /// Forbid growing the file.
+        const Grow        = libc::F_SEAL_GROW   as u32;
+        /// Forbid writing the file, including through shared writable mappings, which must not exist when this is added.
+        const Write       = libc::F_SEAL_WRITE  as u32;
+        /// Forbid writing the file through new descriptors or mappings, but not extant shared writable mappings.
+        const FutureWrite = 0x10;
+    }
+}
+
+#[cfg(target_os = "linux")]
+bitflags! {
+    /// Flags modifying behavior of `File::allocate`
+    pub struct AllocFlags: usize {
+        /// Do not change the file size, even if the range is beyond the end of file.
+        const KeepSize      = libc::FALLOC_FL_KEEP_SIZE      as usize;
+        /// Deallocate the range, which then reads as zeros; needs `KeepSize`.
+        const PunchHole     = libc::FALLOC_FL_PUNCH_HOLE     as usize;
+        /// Remove the range, shifting the rest of the file down; the range must be aligned to the filesystem block size.
+        const CollapseRange = libc::FALLOC_FL_COLLAPSE_RANGE as usize;
+        /// Zero the range, allocating it if need be.
+        const ZeroRange     = libc::FALLOC_FL_ZERO_RANGE     as usize;
+        /// Insert a hole at the range, shifting the rest of the file up; the range must be aligned to the filesystem block size.
+        const InsertRange   = libc::FALLOC_FL_INSERT_RANGE   as usize;
+    }
+}
+
+/// How the caller will access a range of a file, for `File::advise`
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Advice {
+    /** In no particular way                           */ Normal     = libc::POSIX_FADV_NORMAL     as isize,
+    /** Randomly                                       */ Random     = libc::POSIX_FADV_RANDOM     as isize,
+    /** Sequentially, from lower to higher offsets     */ Sequential = libc::POSIX_FADV_SEQUENTIAL as isize,
+    /** Soon, so the system might read it ahead        */ WillNeed   = libc::POSIX_FADV_WILLNEED   as isize,
+    /** Not soon, so the system might uncache it       */ DontNeed   = libc::POSIX_FADV_DONTNEED   as isize,
+    /** Only once                                      */ NoReuse    = libc::POSIX_FADV_NOREUSE    as isize,
+}
+
+/// Where to set the file offset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Whence {
+    /** At the given offset                                */ Start(u64),
+    /** At the given offset from the end of the file       */ End(i64),
+    /** At the given offset from the current file offset   */ Here(i64),
+    /** At the first data at or after the given offset     */
+    #[cfg(target_os = "linux")] Data(u64),
+    /** At the first hole at or after the given offset     */
+    #[cfg(target_os = "linux")] Hole(u64),
+}
+
+/// Type of file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileType {
+    /** Regular file                          */ Regular,
+    /** Directory                             */ Directory,
+    /** Symbolic link                         */ Symlink,
+    /** Character device                      */ CharDevice,
+    /** Block device                          */ BlockDevice,
+    /** Named pipe                            */ Fifo,
+    /** Unix-domain socket                    */ Socket,
+    /** Unknown, e.g. not reported by the filesystem */ Unknown,
+}
+
+impl FileType {
+    /// Return the type given in the `S_IFMT` bits of a file mode.
+    #[inline]
+    pub fn from_mode(mode: libc::mode_t) -> Self {
+        match mode & libc::S_IFMT {
+            libc::S_IFREG  => FileType::Regular,
+            libc::S_IFDIR  => FileType::Directory,
+            libc::S_IFLNK  => FileType::Symlink,
+            libc::S_IFCHR  => FileType::CharDevice,
+            libc::S_IFBLK  => FileType::BlockDevice,
+            libc::S_IFIFO  => FileType::Fifo,
+            libc::S_IFSOCK => FileType::Socket,
+            _              => FileType::Unknown,
+        }
+    }
+
+    /// Return the type given in the `d_type` of a directory entry.
+    #[inline]
+    pub fn from_dirent_type(typ: u8) -> Self {
+        match typ {
+            libc::DT_REG  => FileType::Regular,
+            libc::DT_DIR  => FileType::Directory,
+            libc::DT_LNK  => FileType::Symlink,
+            libc::DT_CHR  => FileType::CharDevice,
+            libc::DT_BLK  => FileType::BlockDevice,
+            libc::DT_FIFO => FileType::Fifo,
+            libc::DT_SOCK => FileType::Socket,
+            _             => FileType::Unknown,
+        }
     }
 }
 
 /// File information
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Stat {
-    /** ID of device containing file  */ pub dev:     libc::dev_t,
+    /** ID of device containing file  */ pub dev:     Device,
     /** Inode number                  */ pub ino:     libc::ino_t,
-    /** File type and mode            */ pub mode:    Mode,
+    /** File type                     */ pub typ:     FileType,
+    /** File mode                     */ pub mode:    Mode,
     /** Number of links               */ pub nlink:   libc::nlink_t,
+    /** ID of device, if file is one  */ pub rdev:    Device,
     /** User ID of owner              */ pub uid:     libc::uid_t,
     /** Group ID of owner             */ pub gid:     libc::gid_t,
     /** Total size, in bytes          */ pub size:    libc::off_t,
@@ -480,10 +1508,12 @@ impl From<libc::stat> for Stat {
     #[inline(always)]
     fn from(st: libc::stat) -> Self {
         Stat {
-            dev: st.st_dev,
+            dev: Device(st.st_dev),
             ino: st.st_ino,
+            typ: FileType::from_mode(st.st_mode),
             mode: Mode::from_bits_truncate(st.st_mode as _),
             nlink: st.st_nlink,
+            rdev: Device(st.st_rdev),
             uid: st.st_uid,
             gid: st.st_gid,
             size: st.st_size,
@@ -496,7 +1526,410 @@ impl From<libc::stat> for Stat {
     }
 }
 
+#[cfg(target_os = "linux")]
+bitflags! {
+    /// Which fields of `Statx` to return, or were returned
+    pub struct StatxMask: u32 {
+        #[allow(missing_docs)] const Type   = 0x0001;
+        #[allow(missing_docs)] const Mode   = 0x0002;
+        #[allow(missing_docs)] const NLink  = 0x0004;
+        #[allow(missing_docs)] const Uid    = 0x0008;
+        #[allow(missing_docs)] const Gid    = 0x0010;
+        #[allow(missing_docs)] const ATime  = 0x0020;
+        #[allow(missing_docs)] const MTime  = 0x0040;
+        #[allow(missing_docs)] const CTime  = 0x0080;
+        #[allow(missing_docs)] const Ino    = 0x0100;
+        #[allow(missing_docs)] const Size   = 0x0200;
+        #[allow(missing_docs)] const Blocks = 0x0400;
+        /// All the fields `stat_at` returns
+        const BasicStats = 0x07FF;
+        #[allow(missing_docs)] const BTime  = 0x0800;
+        #[allow(missing_docs)] const MntId  = 0x1000;
+    }
+}
+
+#[cfg(target_os = "linux")]
+bitflags! {
+    /// File attributes
+    pub struct StatxAttrs: u64 {
+        /// The file is compressed by the filesystem.
+        const Compressed = 0x00000004;
+        /// The file can not be modified, deleted, or renamed, nor linked to.
+        const Immutable  = 0x00000010;
+        /// The file can only be opened in append mode for writing.
+        const Append     = 0x00000020;
+        /// The file is not a candidate for backup.
+        const NoDump     = 0x00000040;
+        /// The file is encrypted, and needs a key to be read or written.
+        const Encrypted  = 0x00000800;
+        /// The file is an automount trigger.
+        const Automount  = 0x00001000;
+        /// The file is the root of a mount.
+        const MountRoot  = 0x00002000;
+        /// The file has fs-verity enabled: it can not be written, and reads are verified.
+        const Verity     = 0x00100000;
+        /// The file is in DAX state: accesses bypass the page cache.
+        const Dax        = 0x00200000;
+    }
+}
+
+/// Extended file information
+///
+/// Fields not in `mask` are meaningless.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Statx {
+    /** Which fields are meaningful               */ pub mask:            StatxMask,
+    /** Block size for filesystem I/O             */ pub blksize:         u32,
+    /** File attributes                           */ pub attributes:      StatxAttrs,
+    /** Which attributes the filesystem supports  */ pub attributes_mask: StatxAttrs,
+    /** Number of links                           */ pub nlink:           u32,
+    /** User ID of owner                          */ pub uid:             libc::uid_t,
+    /** Group ID of owner                         */ pub gid:             libc::gid_t,
+    /** File type                                 */ pub typ:             FileType,
+    /** File mode                                 */ pub mode:            Mode,
+    /** Inode number                              */ pub ino:             u64,
+    /** Total size, in bytes                      */ pub size:            u64,
+    /** Number of allocated 512-byte blocks       */ pub blocks:          u64,
+    /** Time of last access                       */ pub atime:           EpochTime,
+    /** Time of creation                          */ pub btime:           EpochTime,
+    /** Time of last status change                */ pub ctime:           EpochTime,
+    /** Time of last modification                 */ pub mtime:           EpochTime,
+    /** Major ID of device, if file is a device   */ pub rdev_major:      u32,
+    /** Minor ID of device, if file is a device   */ pub rdev_minor:      u32,
+    /** Major ID of device containing file        */ pub dev_major:       u32,
+    /** Minor ID of device containing file        */ pub dev_minor:       u32,
+    /** ID of mount containing file               */ pub mnt_id:          u64,
+}
+
+#[cfg(target_os = "linux")]
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct statx_timestamp {
+    tv_sec: i64,
+    tv_nsec: u32,
+    __reserved: i32,
+}
+
+#[cfg(target_os = "linux")]
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct statx {
+    stx_mask: u32,
+    stx_blksize: u32,
+    stx_attributes: u64,
+    stx_nlink: u32,
+    stx_uid: u32,
+    stx_gid: u32,
+    stx_mode: u16,
+    __spare0: u16,
+    stx_ino: u64,
+    stx_size: u64,
+    stx_blocks: u64,
+    stx_attributes_mask: u64,
+    stx_atime: statx_timestamp,
+    stx_btime: statx_timestamp,
+    stx_ctime: statx_timestamp,
+    stx_mtime: statx_timestamp,
+    stx_rdev_major: u32,
+    stx_rdev_minor: u32,
+    stx_dev_major: u32,
+    stx_dev_minor: u32,
+    stx_mnt_id: u64,
+    __spare2: [u64; 13],
+}
+
+#[cfg(target_os = "linux")]
+impl From<statx> for Statx {
+    #[inline(always)]
+    fn from(stx: statx) -> Self {
+        let time = |ts: statx_timestamp| EpochTime::from_s_ns(ts.tv_sec as _, ts.tv_nsec as _);
+        Statx {
+            mask: StatxMask::from_bits_truncate(stx.stx_mask),
+            blksize: stx.stx_blksize,
+            attributes: StatxAttrs::from_bits_truncate(stx.stx_attributes),
+            attributes_mask: StatxAttrs::from_bits_truncate(stx.stx_attributes_mask),
+            nlink: stx.stx_nlink,
+            uid: stx.stx_uid,
+            gid: stx.stx_gid,
+            typ: FileType::from_mode(stx.stx_mode as _),
+            mode: Mode::from_bits_truncate(stx.stx_mode),
+            ino: stx.stx_ino,
+            size: stx.stx_size,
+            blocks: stx.stx_blocks,
+            atime: time(stx.stx_atime),
+            btime: time(stx.stx_btime),
+            ctime: time(stx.stx_ctime),
+            mtime: time(stx.stx_mtime),
+            rdev_major: stx.stx_rdev_major,
+            rdev_minor: stx.stx_rdev_minor,
+            dev_major: stx.stx_dev_major,
+            dev_minor: stx.stx_dev_minor,
+            mnt_id: stx.stx_mnt_id,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl From<Stat> for Statx {
+    #[inline]
+    fn from(st: Stat) -> Self {
+        Statx {
+            mask: StatxMask::BasicStats,
+            blksize: st.blksize as _,
+            attributes: StatxAttrs::empty(),
+            attributes_mask: StatxAttrs::empty(),
+            nlink: st.nlink as _,
+            uid: st.uid,
+            gid: st.gid,
+            typ: st.typ,
+            mode: st.mode,
+            ino: st.ino as _,
+            size: st.size as _,
+            blocks: st.blocks as _,
+            atime: st.atime,
+            btime: EpochTime::from_ns_since_epoch(0),
+            ctime: st.ctime,
+            mtime: st.mtime,
+            rdev_major: st.rdev.major(),
+            rdev_minor: st.rdev.minor(),
+            dev_major: st.dev.major(),
+            dev_minor: st.dev.minor(),
+            mnt_id: 0,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Statx {
+    /// Return the ID of the device containing the file.
+    #[inline]
+    pub fn dev(&self) -> Device { Device::new(self.dev_major, self.dev_minor) }
+
+    /// Return the ID of the device, if the file is one.
+    #[inline]
+    pub fn rdev(&self) -> Device { Device::new(self.rdev_major, self.rdev_minor) }
+}
+
+/// Type of filesystem, as its magic number says
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FsType {
+    /** ext2, ext3, or ext4, which share a magic number */ Ext4,
+    /** XFS                                              */ Xfs,
+    /** Btrfs                                            */ Btrfs,
+    /** tmpfs                                            */ Tmpfs,
+    /** procfs                                           */ Proc,
+    /** overlayfs                                        */ Overlay,
+    /** NFS                                              */ Nfs,
+    /** Another type, of the given magic number          */ Other(u64),
+}
+
+#[cfg(target_os = "linux")]
+impl FsType {
+    const magics: [(Self, u64); 7] = [
+        (FsType::Ext4,    0xEF53),
+        (FsType::Xfs,     0x58465342),
+        (FsType::Btrfs,   0x9123683E),
+        (FsType::Tmpfs,   0x01021994),
+        (FsType::Proc,    0x9FA0),
+        (FsType::Overlay, 0x794C7630),
+        (FsType::Nfs,     0x6969),
+    ];
+
+    /// Return the type of the given magic number.
+    #[inline]
+    pub fn from_magic(magic: u64) -> Self {
+        Self::magics.iter().find(|&&(_, m)| m == magic).map_or(FsType::Other(magic), |&(typ, _)| typ)
+    }
+
+    /// Return the magic number of the type.
+    #[inline]
+    pub fn magic(self) -> u64 {
+        match self {
+            FsType::Other(magic) => magic,
+            typ => Self::magics.iter().find(|&&(t, _)| t == typ).map_or(0, |&(_, m)| m),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+bitflags! {
+    /// Flags with which a filesystem is mounted
+    pub struct MountFlags: u64 {
+        /// Read-only
+        const RdOnly      = 0x0001;
+        /// Set-ID bits are ignored.
+        const NoSuid      = 0x0002;
+        /// Device files can not be accessed.
+        const NoDev       = 0x0004;
+        /// Programs can not be executed.
+        const NoExec      = 0x0008;
+        /// Writes are synchronous.
+        const Synchronous = 0x0010;
+        /// Mandatory locks are allowed.
+        const MandLock    = 0x0040;
+        /// Times of last access are not updated.
+        const NoATime     = 0x0400;
+        /// Times of last access of directories are not updated.
+        const NoDirATime  = 0x0800;
+        /// Times of last access are updated only if older than times of last modification or status change.
+        const RelATime    = 0x1000;
+    }
+}
+
+/// Filesystem information
+///
+/// Counts of blocks are in units of `frsize`.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FsStat {
+    /** Type of filesystem                            */ pub typ:     FsType,
+    /** Block size for filesystem I/O                 */ pub bsize:   u64,
+    /** Fragment size, in which blocks are counted    */ pub frsize:  u64,
+    /** Total number of blocks                        */ pub blocks:  u64,
+    /** Number of free blocks                         */ pub bfree:   u64,
+    /** Number of free blocks for unprivileged users  */ pub bavail:  u64,
+    /** Total number of inodes                        */ pub files:   u64,
+    /** Number of free inodes                         */ pub ffree:   u64,
+    /** Filesystem ID                                 */ pub fsid:    [i32; 2],
+    /** Maximum length of a file name                 */ pub namelen: u64,
+    /** Mount flags                                   */ pub flags:   MountFlags,
+}
+
+#[cfg(target_os = "linux")]
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct statfs {
+    f_type: usize,
+    f_bsize: usize,
+    f_blocks: usize,
+    f_bfree: usize,
+    f_bavail: usize,
+    f_files: usize,
+    f_ffree: usize,
+    f_fsid: [i32; 2],
+    f_namelen: usize,
+    f_frsize: usize,
+    f_flags: usize,
+    f_spare: [usize; 4],
+}
+
+#[cfg(target_os = "linux")]
+impl From<statfs> for FsStat {
+    #[inline(always)]
+    fn from(st: statfs) -> Self {
+        FsStat {
+            typ: FsType::from_magic(st.f_type as u32 as u64),
+            bsize: st.f_bsize as _,
+            frsize: st.f_frsize as _,
+            blocks: st.f_blocks as _,
+            bfree: st.f_bfree as _,
+            bavail: st.f_bavail as _,
+            files: st.f_files as _,
+            ffree: st.f_ffree as _,
+            fsid: st.f_fsid,
+            namelen: st.f_namelen as _,
+            flags: MountFlags::from_bits_truncate(st.f_flags as _),
+        }
+    }
+}
+
+/// Device ID
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Device(pub libc::dev_t);
+
+#[cfg(target_os = "linux")]
+impl Device {
+    /// Make a device ID of its major and minor IDs.
+    #[inline]
+    pub const fn new(major: u32, minor: u32) -> Self {
+        let (major, minor) = (major as libc::dev_t, minor as libc::dev_t);
+        Device((major & 0xFFF) << 8 | (major & !0xFFF) << 32 | minor & 0xFF | (minor & !0xFF) << 12)
+    }
+
+    /// Return the major ID, which says which driver the device is of.
+    #[inline]
+    pub const fn major(self) -> u32 { ((self.0 >> 8) & 0xFFF | (self.0 >> 32) & !0xFFF) as u32 }
+
+    /// Return the minor ID, which says which device of its driver it is.
+    #[inline]
+    pub const fn minor(self) -> u32 { (self.0 & 0xFF | (self.0 >> 12) & !0xFF) as u32 }
+}
+
 #[cfg(target_os = "linux")]
 const AT_EMPTY_PATH: libc::c_int = libc::AT_EMPTY_PATH;
 #[cfg(not(target_os = "linux"))]
 const AT_EMPTY_PATH: libc::c_int = 0;
+
+// Our libc lacks this on Linux, where it shares its value with `AT_REMOVEDIR`, so it has its own flags type.
+#[cfg(target_os = "linux")]
+const AT_EACCESS: libc::c_int = 0x200;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Make a temporary directory, call `f` with it, and remove it.
+    fn with_temp_dir<F: FnOnce(&File)>(f: F) {
+        let mut templ = *b"/tmp/unix-test-XXXXXXXXXXXX\0";
+        let n = templ.len();
+        let templ = <&mut Str>::try_from(&mut templ[..]).unwrap();
+        ::dir::mktemp_at(None, templ, n - 13..n - 1, &mut new_rng().unwrap()).unwrap();
+        let dir = ::dir::open_at(None, templ, O_CLOEXEC).unwrap();
+        f(&dir);
+        ::dir::remove_all_at(None, templ).unwrap();
+    }
+
+    #[test]
+    fn readlink() {
+        with_temp_dir(|dir| {
+            symlink_at(str0!("target"), Some(dir), str0!("l")).unwrap();
+            assert_eq!(b"target", &readlink_at(Some(dir), str0!("l"), &mut [0; 0x10]).unwrap()[..]);
+            assert_eq!(Err(Error::ERANGE), readlink_at(Some(dir), str0!("l"), &mut [0; 6]).map(|_| ()));
+        });
+    }
+
+    #[test]
+    fn seek() {
+        with_temp_dir(|dir| {
+            let mut f = open_at(Some(dir), str0!("f"), OpenMode::RdWr | O_CLOEXEC, Some(Mode::from_bits_truncate(0o600))).unwrap();
+            assert_eq!(5, f.write(b"hello").unwrap());
+            assert_eq!(1, f.seek_to(Whence::Start(1)).unwrap());
+            assert_eq!(3, f.seek_to(Whence::Here(2)).unwrap());
+            assert_eq!(4, f.seek_to(Whence::End(-1)).unwrap());
+            assert_eq!(0, Seek::seek(&mut f, SeekFrom::Start(0)).unwrap());
+            let mut buf = [0; 5];
+            assert_eq!(5, f.read(&mut buf).unwrap());
+            assert_eq!(b"hello", &buf);
+            assert_eq!(Err(Error::EINVAL), f.seek_to(Whence::Here(-6)));
+        });
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn access() {
+        with_temp_dir(|dir| {
+            symlink_at(str0!("missing"), Some(dir), str0!("l")).unwrap();
+            assert_eq!(Err(Error::ENOENT), access_at(Some(dir), str0!("l"), Permission::empty(), AccessFlags::Follow));
+            match access_at(Some(dir), str0!("l"), Permission::empty(), AccessFlags::empty()) {
+                Ok(()) | Err(Error::ENOSYS) => (),
+                Err(e) => panic!("{:?}", e),
+            }
+            access_at(Some(dir), str0!("."), Permission::Read | Permission::Exec, AccessFlags::Effective).unwrap();
+        });
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn readlink_vec_long() {
+        with_temp_dir(|dir| {
+            let mut target = [b'a'; 0x301];
+            target[0x300] = 0;
+            let target = <&Str>::try_from(&target[..]).unwrap();
+            symlink_at(target, Some(dir), str0!("l")).unwrap();
+            assert_eq!(&target[..], &readlink_at_vec(Some(dir), str0!("l")).unwrap()[..]);
+        });
+    }
+}
@@ -0,0 +1,154 @@
+//! Transfer of data between files without copying them through user memory
+//!
+//! Functions here which take an offset as `Option<&mut u64>` use and advance it if it is `Some`, else the file offset.
+
+use core::ptr;
+
+use {Error, File};
+use super::Whence;
+
+bitflags! {
+    /// Flags modifying behavior of `splice` and `tee`
+    pub struct SpliceFlags: usize {
+        /// Move pages rather than copy them, if possible; this is only a hint.
+        const Move     = 0x01;
+        /// Fail with [`EAGAIN`](../../struct.Error.html#associatedconstant.EAGAIN) rather than block on the pipes.
+        const NonBlock = 0x02;
+        /// More data will follow in a later call, as with `MSG_MORE` on a socket.
+        const More     = 0x04;
+    }
+}
+
+/// Copy at most `len` bytes from `src` to `dst`, and return how many bytes were copied.
+///
+/// The filesystem may share the data between the files, or copy them on the device, rather than copy them through the system's memory.
+#[inline]
+pub fn copy_file_range(src: &File, src_off: Option<&mut u64>,
+                       dst: &File, dst_off: Option<&mut u64>, len: usize) -> Result<usize, Error> {
+    unsafe { esyscall!(COPY_FILE_RANGE, src.fd(), opt_ptr(src_off), dst.fd(), opt_ptr(dst_off), len, 0) }
+}
+
+/// Copy at most `len` bytes from `src` to `dst`, and return how many bytes were copied.
+///
+/// `dst` is written at its file offset; `src` must support `mmap`-like operations, so it can not be a socket.
+#[inline]
+pub fn sendfile(src: &File, src_off: Option<&mut u64>, dst: &File, len: usize) -> Result<usize, Error> {
+    unsafe { esyscall!(SENDFILE, dst.fd(), src.fd(), opt_ptr(src_off), len) }
+}
+
+/// Move at most `len` bytes from `src` to `dst`, and return how many bytes were moved.
+///
+/// At least one of `src` and `dst` must be a pipe, and its offset must be `None`.
+#[inline]
+pub fn splice(src: &File, src_off: Option<&mut u64>,
+              dst: &File, dst_off: Option<&mut u64>, len: usize, flags: SpliceFlags) -> Result<usize, Error> {
+    unsafe { esyscall!(SPLICE, src.fd(), opt_ptr(src_off), dst.fd(), opt_ptr(dst_off), len, flags.bits) }
+}
+
+/// Copy at most `len` bytes from the pipe `src` to the pipe `dst`, without consuming them from `src`, and return how many bytes were copied.
+#[inline]
+pub fn tee(src: &File, dst: &File, len: usize, flags: SpliceFlags) -> Result<usize, Error> {
+    unsafe { esyscall!(TEE, src.fd(), dst.fd(), len, flags.bits) }
+}
+
+/// Copy all the data of `src` to `dst`, both from offset 0, and return how many bytes were copied.
+///
+/// This tries, in order, until one works: sharing the data between the files (reflink), `copy_file_range`, `sendfile`, and reading and writing through a buffer.
+/// `dst` is truncated to the length of `src`, so none of its former data are left after.
+/// The file offset of `dst` is unspecified after.
+///
+/// Fails with [`EBADF`](../../struct.Error.html#associatedconstant.EBADF) if `dst` is in append mode, as `copy_file_range` would,
+/// since it could then not be written at the offsets wanted.
+pub fn copy_all(src: &File, dst: &File) -> Result<u64, Error> {
+    if dst.is_append()? { return Err(Error::EBADF) }
+
+    const FICLONE: usize = 0x40049409;
+    match unsafe { esyscall_!(IOCTL, dst.fd(), FICLONE, src.fd()) } {
+        Ok(()) => return src.stat().map(|st| st.size as u64),
+        Err(Error::EOPNOTSUPP) | Err(Error::EXDEV) | Err(Error::EINVAL) | Err(Error::ENOTTY) => (),
+        Err(e) => return Err(e),
+    }
+
+    let off = copy_data(src, dst)?;
+    dst.truncate(off)?;
+    Ok(off)
+}
+
+/// Copy the data of `src` to `dst` as `copy_all` does after trying reflink, but without truncating `dst`.
+fn copy_data(src: &File, dst: &File) -> Result<u64, Error> {
+    let mut off = 0;
+    loop {
+        let mut dst_off = off;
+        match copy_file_range(src, Some(&mut off), dst, Some(&mut dst_off), isize::max_value() as usize) {
+            Ok(0) => return Ok(off),
+            Ok(_) => (),
+            // Some failures only show after some data were copied, so go on from there by other means.
+            Err(Error::ENOSYS) | Err(Error::EXDEV) | Err(Error::EINVAL) | Err(Error::EOPNOTSUPP) => break,
+            Err(e) => return Err(e),
+        }
+    }
+
+    dst.seek_to(Whence::Start(off))?;
+    loop {
+        match sendfile(src, Some(&mut off), dst, isize::max_value() as usize) {
+            Ok(0) => return Ok(off),
+            Ok(_) => (),
+            Err(Error::ENOSYS) | Err(Error::EINVAL) => break,
+            Err(e) => return Err(e),
+        }
+    }
+
+    let mut buf = [0; 0x2000];
+    loop {
+        let n = src.read_at(&mut buf, off)?;
+        if 0 == n { return Ok(off) }
+        let mut k = 0;
+        while k < n {
+            k += match dst.write_at(&buf[k..n], off + k as u64)? {
+                0 => return Err(Error::EIO),
+                m => m,
+            };
+        }
+        off += n as u64;
+    }
+}
+
+#[inline]
+fn opt_ptr(opt_off: Option<&mut u64>) -> *mut u64 {
+    opt_off.map_or(ptr::null_mut(), |off| off as *mut u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use fallible::TryClone;
+    use io::Write;
+    use file::{Mode, OpenFlags, TempFile};
+    use super::*;
+
+    fn temp_file() -> File {
+        let dir = ::dir::open_at(None, str0!("/tmp"), OpenFlags::O_CLOEXEC).unwrap();
+        let tf = TempFile::new_at(Some(&dir), Mode::from_bits_truncate(0o600), OpenFlags::empty()).unwrap();
+        tf.file().try_clone().unwrap()
+    }
+
+    #[test]
+    fn copy_all_truncates() {
+        let mut src = temp_file();
+        let mut dst = temp_file();
+        assert_eq!(5, src.write(b"hello").unwrap());
+        assert_eq!(11, dst.write(b"hello world").unwrap());
+        assert_eq!(5, copy_all(&src, &dst).unwrap());
+        assert_eq!(5, dst.stat().unwrap().size);
+        let mut buf = [0; 8];
+        assert_eq!(5, dst.read_at(&mut buf, 0).unwrap());
+        assert_eq!(b"hello", &buf[..5]);
+    }
+
+    #[test]
+    fn copy_all_append() {
+        let src = temp_file();
+        let dst = temp_file();
+        dst.set_append(true).unwrap();
+        assert_eq!(Err(Error::EBADF), copy_all(&src, &dst));
+    }
+}
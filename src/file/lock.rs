@@ -0,0 +1,150 @@
+//! Advisory file locks
+//!
+//! Whole-file locks, by `flock`, belong to the open file description: they are released when every descriptor of it is closed.
+//! Byte-range locks belong to whichever `Owner` is given; see there.
+//! Each lock is released when its guard is dropped.
+
+use core::mem;
+use libc;
+
+use {Error, File};
+use process;
+
+/// Kind of lock
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kind {
+    /** Shared with other shared locks, for reading  */ Shared,
+    /** Held by at most one owner, for writing       */ Exclusive,
+}
+
+/// Owner of a byte-range lock
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Owner {
+    /// The calling process: a POSIX record lock, which is released when the process closes any descriptor of the file, and not inherited over `fork`.
+    Process,
+    /// The open file description: an OFD lock, which is released when every descriptor of it is closed.
+    #[cfg(target_os = "linux")]
+    OpenFile,
+}
+
+impl Owner {
+    #[inline]
+    fn cmds(self) -> (libc::c_int, libc::c_int, libc::c_int) {
+        match self {
+            Owner::Process => (libc::F_GETLK, libc::F_SETLK, libc::F_SETLKW),
+            #[cfg(target_os = "linux")]
+            Owner::OpenFile => (36, 37, 38),
+        }
+    }
+}
+
+/// Whole-file lock, released on drop
+#[derive(Debug)]
+pub struct FileLock<'a> {
+    file: &'a File,
+}
+
+impl<'a> Drop for FileLock<'a> {
+    #[inline]
+    fn drop(&mut self) { unsafe { syscall!(FLOCK, self.file.fd(), libc::LOCK_UN) }; }
+}
+
+/// Byte-range lock, released on drop
+#[derive(Debug)]
+pub struct RangeLock<'a> {
+    file: &'a File,
+    owner: Owner,
+    start: u64,
+    len: u64,
+}
+
+impl<'a> Drop for RangeLock<'a> {
+    #[inline]
+    fn drop(&mut self) {
+        let fl = flock(libc::F_UNLCK, self.start, self.len);
+        unsafe { syscall!(FCNTL, self.file.fd(), self.owner.cmds().1, &fl as *const libc::flock) };
+    }
+}
+
+/// Byte-range lock which conflicts with one the caller asked about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Conflict {
+    /** Kind of lock                                          */ pub kind: Kind,
+    /** Start of range                                        */ pub start: u64,
+    /** Length of range, or 0 if it goes to the end of file */ pub len: u64,
+    /** Owning process, or `None` if the lock is an OFD lock  */ pub pid: Option<process::Id>,
+}
+
+impl File {
+    /// Lock the whole file, waiting until no conflicting lock is held.
+    #[inline]
+    pub fn lock(&self, kind: Kind) -> Result<FileLock, Error> {
+        unsafe { esyscall_!(FLOCK, self.fd(), flock_op(kind)) }.map(|()| FileLock { file: self })
+    }
+
+    /// Lock the whole file, or return `None` if a conflicting lock is held.
+    #[inline]
+    pub fn try_lock(&self, kind: Kind) -> Result<Option<FileLock>, Error> {
+        match unsafe { esyscall_!(FLOCK, self.fd(), flock_op(kind) | libc::LOCK_NB) } {
+            Ok(()) => Ok(Some(FileLock { file: self })),
+            Err(Error::EWOULDBLOCK) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Lock `len` bytes of the file from `start`, or to the end of file if `len` is 0, waiting until no conflicting lock is held.
+    #[inline]
+    pub fn lock_range(&self, owner: Owner, kind: Kind, start: u64, len: u64) -> Result<RangeLock, Error> {
+        let fl = flock(lock_type(kind), start, len);
+        unsafe { esyscall_!(FCNTL, self.fd(), owner.cmds().2, &fl as *const libc::flock) }
+            .map(|()| RangeLock { file: self, owner, start, len })
+    }
+
+    /// Lock `len` bytes of the file from `start`, or to the end of file if `len` is 0, or return `None` if a conflicting lock is held.
+    #[inline]
+    pub fn try_lock_range(&self, owner: Owner, kind: Kind, start: u64, len: u64) -> Result<Option<RangeLock>, Error> {
+        let fl = flock(lock_type(kind), start, len);
+        match unsafe { esyscall_!(FCNTL, self.fd(), owner.cmds().1, &fl as *const libc::flock) } {
+            Ok(()) => Ok(Some(RangeLock { file: self, owner, start, len })),
+            Err(Error::EAGAIN) | Err(Error::EACCES) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Return a lock which would conflict with locking `len` bytes of the file from `start`, or to the end of file if `len` is 0, if any.
+    #[inline]
+    pub fn query_lock(&self, owner: Owner, kind: Kind, start: u64, len: u64) -> Result<Option<Conflict>, Error> {
+        let mut fl = flock(lock_type(kind), start, len);
+        unsafe { esyscall_!(FCNTL, self.fd(), owner.cmds().0, &mut fl as *mut libc::flock)? };
+        Ok(match fl.l_type as libc::c_int {
+            libc::F_UNLCK => None,
+            t => Some(Conflict {
+                kind: if libc::F_RDLCK == t { Kind::Shared } else { Kind::Exclusive },
+                start: fl.l_start as _,
+                len: fl.l_len as _,
+                pid: if fl.l_pid > 0 { Some(fl.l_pid as _) } else { None },
+            }),
+        })
+    }
+}
+
+#[inline]
+fn flock_op(kind: Kind) -> libc::c_int {
+    match kind { Kind::Shared => libc::LOCK_SH, Kind::Exclusive => libc::LOCK_EX }
+}
+
+#[inline]
+fn lock_type(kind: Kind) -> libc::c_int {
+    match kind { Kind::Shared => libc::F_RDLCK, Kind::Exclusive => libc::F_WRLCK }
+}
+
+#[inline]
+fn flock(typ: libc::c_int, start: u64, len: u64) -> libc::flock {
+    // `l_pid` must be 0 for OFD locks.
+    let mut fl: libc::flock = unsafe { mem::zeroed() };
+    fl.l_type = typ as _;
+    fl.l_whence = libc::SEEK_SET as _;
+    fl.l_start = start as _;
+    fl.l_len = len as _;
+    fl
+}
@@ -0,0 +1,192 @@
+//! Extended attributes
+//!
+//! Functions here which take a path and `at_flags` act on a symbolic link there itself unless `at_flags` contains `Follow`.
+//! Values and lists of names are read into buffers the caller gives, which fail with
+//! [`ERANGE`](../../struct.Error.html#associatedconstant.ERANGE) if too small; an empty buffer rather returns the size needed.
+
+use core::convert::TryFrom;
+use core::ptr;
+use libc;
+
+use {Error, File, Str};
+use super::{AtFlags, from_opt_dir, proc_fd_path};
+
+/// Namespace of an extended attribute, given by the prefix of its name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Namespace {
+    /** `user.`: for any use, by whoever may access the file                */ User,
+    /** `trusted.`: only for processes with `CAP_SYS_ADMIN`                 */ Trusted,
+    /** `security.`: for security modules, e.g. SELinux labels and capabilities */ Security,
+    /** `system.`: for the system, e.g. ACLs                                */ System,
+}
+
+impl Namespace {
+    /// Return the prefix of names in the namespace, including the `.`.
+    #[inline]
+    pub fn prefix(self) -> &'static [u8] {
+        match self {
+            Namespace::User     => b"user.",
+            Namespace::Trusted  => b"trusted.",
+            Namespace::Security => b"security.",
+            Namespace::System   => b"system.",
+        }
+    }
+
+    /// Split `name` into its namespace and the rest, or return `None` if it is in none of these.
+    #[inline]
+    pub fn split(name: &[u8]) -> Option<(Self, &[u8])> {
+        [Namespace::User, Namespace::Trusted, Namespace::Security, Namespace::System].iter()
+            .find(|ns| name.starts_with(ns.prefix()))
+            .map(|&ns| (ns, &name[ns.prefix().len()..]))
+    }
+}
+
+bitflags! {
+    /// Flags modifying behavior of setting an extended attribute
+    pub struct SetFlags: usize {
+        /// Fail with [`EEXIST`](../../struct.Error.html#associatedconstant.EEXIST) if the attribute already is.
+        const Create  = libc::XATTR_CREATE  as usize;
+        /// Fail with [`ENODATA`](../../struct.Error.html#associatedconstant.ENODATA) if the attribute is not already.
+        const Replace = libc::XATTR_REPLACE as usize;
+    }
+}
+
+/// Iterator over the names in a list of extended attributes
+#[derive(Debug, Clone)]
+pub struct Names<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Names<'a> {
+    /// Iterate over the names in `list`, as read by `list_xattr` or `list_at`.
+    #[inline]
+    pub fn new(list: &'a [u8]) -> Self { Names { buf: list } }
+}
+
+impl<'a> Iterator for Names<'a> {
+    type Item = &'a Str;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a Str> {
+        let n = self.buf.iter().position(|&b| 0 == b)?;
+        let (name, rest) = self.buf.split_at(n + 1);
+        self.buf = rest;
+        <&Str>::try_from(name).ok()
+    }
+}
+
+impl File {
+    /// Read the value of the extended attribute `name` into `buf`, and return its length.
+    #[inline]
+    pub fn get_xattr(&self, name: &Str, buf: &mut [u8]) -> Result<usize, Error> {
+        unsafe { esyscall!(FGETXATTR, self.fd(), name.as_ptr(), buf_ptr(buf), buf.len()) }
+    }
+
+    /// Set the value of the extended attribute `name`.
+    #[inline]
+    pub fn set_xattr(&self, name: &Str, value: &[u8], flags: SetFlags) -> Result<(), Error> {
+        unsafe { esyscall_!(FSETXATTR, self.fd(), name.as_ptr(), value.as_ptr(), value.len(), flags.bits) }
+    }
+
+    /// Read the list of names of extended attributes into `buf`, and return its length; iterate over it with `Names`.
+    #[inline]
+    pub fn list_xattr(&self, buf: &mut [u8]) -> Result<usize, Error> {
+        unsafe { esyscall!(FLISTXATTR, self.fd(), buf_ptr(buf), buf.len()) }
+    }
+
+    /// Remove the extended attribute `name`.
+    #[inline]
+    pub fn remove_xattr(&self, name: &Str) -> Result<(), Error> {
+        unsafe { esyscall_!(FREMOVEXATTR, self.fd(), name.as_ptr()) }
+    }
+}
+
+/// Read the value of the extended attribute `name` of the file at `path` into `buf`, and return its length.
+#[inline]
+pub fn get_at(opt_dir: Option<&File>, path: &Str, name: &Str, buf: &mut [u8], at_flags: AtFlags) -> Result<usize, Error> {
+    with_path(opt_dir, path, at_flags, |path, follow| unsafe {
+        if follow { esyscall!(GETXATTR,  path.as_ptr(), name.as_ptr(), buf_ptr(buf), buf.len()) }
+        else      { esyscall!(LGETXATTR, path.as_ptr(), name.as_ptr(), buf_ptr(buf), buf.len()) }
+    })
+}
+
+/// Set the value of the extended attribute `name` of the file at `path`.
+#[inline]
+pub fn set_at(opt_dir: Option<&File>, path: &Str, name: &Str, value: &[u8],
+              flags: SetFlags, at_flags: AtFlags) -> Result<(), Error> {
+    with_path(opt_dir, path, at_flags, |path, follow| unsafe {
+        if follow { esyscall_!(SETXATTR,  path.as_ptr(), name.as_ptr(), value.as_ptr(), value.len(), flags.bits) }
+        else      { esyscall_!(LSETXATTR, path.as_ptr(), name.as_ptr(), value.as_ptr(), value.len(), flags.bits) }
+    })
+}
+
+/// Read the list of names of extended attributes of the file at `path` into `buf`, and return its length; iterate over it with `Names`.
+#[inline]
+pub fn list_at(opt_dir: Option<&File>, path: &Str, buf: &mut [u8], at_flags: AtFlags) -> Result<usize, Error> {
+    with_path(opt_dir, path, at_flags, |path, follow| unsafe {
+        if follow { esyscall!(LISTXATTR,  path.as_ptr(), buf_ptr(buf), buf.len()) }
+        else      { esyscall!(LLISTXATTR, path.as_ptr(), buf_ptr(buf), buf.len()) }
+    })
+}
+
+/// Remove the extended attribute `name` of the file at `path`.
+#[inline]
+pub fn remove_at(opt_dir: Option<&File>, path: &Str, name: &Str, at_flags: AtFlags) -> Result<(), Error> {
+    with_path(opt_dir, path, at_flags, |path, follow| unsafe {
+        if follow { esyscall_!(REMOVEXATTR,  path.as_ptr(), name.as_ptr()) }
+        else      { esyscall_!(LREMOVEXATTR, path.as_ptr(), name.as_ptr()) }
+    })
+}
+
+/// Call `f` with a path to the file at `path` which needs no `opt_dir`, and whether to follow a symbolic link there.
+#[inline]
+fn with_path<A, F: FnOnce(&Str, bool) -> Result<A, Error>>
+  (opt_dir: Option<&File>, path: &Str, at_flags: AtFlags, f: F) -> Result<A, Error> {
+    let follow = at_flags.contains(AtFlags::Follow);
+    match opt_dir {
+        None => f(path, follow),
+        // The system calls have no `_at` forms, so reach the file through `/proc`.
+        Some(_) => {
+            let file = unsafe { esyscall!(OPENAT, from_opt_dir(opt_dir), path.as_ptr(),
+                                          libc::O_PATH | libc::O_CLOEXEC | if follow { 0 } else { libc::O_NOFOLLOW }) }
+                .map(|fd| File::new_unchecked(fd as _))?;
+            let mut fd_path_buf = [0; 0x20];
+            f(proc_fd_path(&file, &mut fd_path_buf), true)
+        },
+    }
+}
+
+/// Pass a null pointer for an empty buffer, so the system returns the size needed.
+#[inline]
+fn buf_ptr(buf: &mut [u8]) -> *mut u8 { if buf.is_empty() { ptr::null_mut() } else { buf.as_mut_ptr() } }
+
+#[cfg(test)]
+mod tests {
+    use file::{Mode, OpenFlags, TempFile};
+    use super::*;
+
+    #[test]
+    fn list() {
+        let dir = ::dir::open_at(None, str0!("/tmp"), OpenFlags::O_CLOEXEC).unwrap();
+        let tf = TempFile::new_at(Some(&dir), Mode::from_bits_truncate(0o600), OpenFlags::empty()).unwrap();
+        let f = tf.file();
+        match f.set_xattr(str0!("user.unix-test"), b"value", SetFlags::Create) {
+            Ok(()) => (),
+            // The filesystem may not support user attributes.
+            Err(Error::EOPNOTSUPP) => return,
+            Err(e) => panic!("{:?}", e),
+        }
+
+        let n = f.list_xattr(&mut []).unwrap();
+        assert!(n >= b"user.unix-test\0".len());
+        assert_eq!(Err(Error::ERANGE), f.list_xattr(&mut [0; 1]));
+        let mut buf = [0; 0x100];
+        assert_eq!(n, f.list_xattr(&mut buf).unwrap());
+        assert!(Names::new(&buf[..n]).any(|name| b"user.unix-test" == &name[..]));
+
+        assert_eq!(5, f.get_xattr(str0!("user.unix-test"), &mut []).unwrap());
+        assert_eq!(Err(Error::EEXIST), f.set_xattr(str0!("user.unix-test"), b"", SetFlags::Create));
+        f.remove_xattr(str0!("user.unix-test")).unwrap();
+        assert_eq!(Err(Error::ENODATA), f.get_xattr(str0!("user.unix-test"), &mut []));
+    }
+}
@@ -7,6 +7,8 @@
 
 #![feature(core_intrinsics)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
 #[macro_use]
 extern crate bitflags;
 #[macro_use]
@@ -30,9 +32,11 @@ mod env_;
 pub mod dir;
 pub mod file;
 pub mod mem;
+pub mod net;
 pub mod poll;
 pub mod process;
 pub mod random;
+pub mod signal;
 pub mod time;
 
 mod util;
@@ -101,12 +101,12 @@ impl MapExt for () {
 
     #[inline]
     fn map(&self, perm: Perm, length: usize) -> Result<Map, Error> {
-        unsafe { do_map(-1, ptr::null_mut(), perm, 0, length) }
+        unsafe { do_map(-1, ptr::null_mut(), perm, libc::MAP_ANONYMOUS | libc::MAP_PRIVATE, 0, length) }
     }
 
     #[inline]
     unsafe fn map_at(&self, loc: *mut u8, perm: Perm, length: usize) -> Result<Map, Error> {
-        do_map(-1, loc, perm, 0, length)
+        do_map(-1, loc, perm, libc::MAP_ANONYMOUS | libc::MAP_PRIVATE, 0, length)
     }
 }
 
@@ -116,15 +116,29 @@ unsafe fn do_map_file(f: &File, loc: *mut u8, perm: Perm, seg: Option<Segment>)
     let Segment { offset, length } = seg.unwrap_or(Segment {
         offset: 0, length: try_to_usize(f.stat()?.size as _)?
     });
-    do_map(f.fd(), loc, perm, offset, length)
+    do_map(f.fd(), loc, perm, libc::MAP_SHARED, offset, length)
 }
 
 #[inline]
-unsafe fn do_map(fd: isize, loc: *mut u8, perm: Perm, offset: u64, length: usize) ->
+unsafe fn do_map(fd: isize, loc: *mut u8, perm: Perm, flags: libc::c_int, offset: u64, length: usize) ->
   Result<Map, Error> {
     let ptr = syscall!(MMAP, loc, length, Prot::from(perm).bits,
-                       if loc.is_null() { 0 } else { libc::MAP_FIXED } | libc::MAP_SHARED as i32, fd, offset) as *mut u8;
+                       if loc.is_null() { 0 } else { libc::MAP_FIXED } | flags, fd, offset) as *mut u8;
     if (ptr as usize) > 0x1000usize.wrapping_neg() {
         Err(Error::from(NonZeroUsize::new_unchecked((ptr as usize).wrapping_neg())))
     } else { Ok(Map { ptr: ptr as *mut u8, length }) }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_anonymous() {
+        let mut m = ().map(Perm::Read | Perm::Write, 0x2000).unwrap();
+        assert_eq!(0x2000, m.len());
+        assert!(m.iter().all(|&b| 0 == b));
+        m[0x1FFF] = 0xA5;
+        assert_eq!(0xA5, m[0x1FFF]);
+    }
+}
@@ -0,0 +1,393 @@
+//! Network sockets
+//!
+//! Sockets are `File`s, so they can be read, written, and polled as any other file.
+
+use core::{cmp, fmt, mem, ptr, slice};
+use core::mem::MaybeUninit as MU;
+use libc;
+
+use {Error, File};
+use file::OpenFlags;
+
+/// Communication domain of a socket
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Domain(libc::c_int);
+
+#[allow(missing_docs)]
+impl Domain {
+    pub const Unix : Self = Domain(libc::AF_UNIX);
+    pub const Inet : Self = Domain(libc::AF_INET);
+    pub const Inet6: Self = Domain(libc::AF_INET6);
+}
+
+/// Communication semantics of a socket
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SockType(libc::c_int);
+
+impl SockType {
+    /** Reliable, ordered byte stream                   */ pub const Stream   : Self = SockType(libc::SOCK_STREAM);
+    /** Unreliable, unordered datagrams                 */ pub const Dgram    : Self = SockType(libc::SOCK_DGRAM);
+    /** Reliable, ordered datagrams                     */ pub const SeqPacket: Self = SockType(libc::SOCK_SEQPACKET);
+    /** Raw network protocol access                     */ pub const Raw      : Self = SockType(libc::SOCK_RAW);
+}
+
+/// Address of a socket
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SockAddr {
+    /** Unix-domain address */ Unix(UnixAddr),
+    /** IPv4 address        */ Inet(InetAddr),
+    /** IPv6 address        */ Inet6(Inet6Addr),
+}
+
+impl SockAddr {
+    /// Return the communication domain of the address.
+    #[inline]
+    pub fn domain(&self) -> Domain {
+        match *self {
+            SockAddr::Unix(_)  => Domain::Unix,
+            SockAddr::Inet(_)  => Domain::Inet,
+            SockAddr::Inet6(_) => Domain::Inet6,
+        }
+    }
+
+    #[inline]
+    fn to_c(&self) -> (sockaddr_any, libc::socklen_t) { unsafe {
+        let mut sa: sockaddr_any = mem::zeroed();
+        let len = match *self {
+            SockAddr::Unix(a) => { sa.un = a.sa; a.len }
+            SockAddr::Inet(a) => {
+                sa.in4.sin_family = libc::AF_INET as _;
+                sa.in4.sin_port = a.port.to_be();
+                sa.in4.sin_addr.s_addr = u32::from_be_bytes(a.addr).to_be();
+                mem::size_of::<libc::sockaddr_in>() as _
+            },
+            SockAddr::Inet6(a) => {
+                sa.in6.sin6_family = libc::AF_INET6 as _;
+                sa.in6.sin6_port = a.port.to_be();
+                sa.in6.sin6_flowinfo = a.flowinfo;
+                sa.in6.sin6_addr.s6_addr = a.addr;
+                sa.in6.sin6_scope_id = a.scope_id;
+                mem::size_of::<libc::sockaddr_in6>() as _
+            },
+        };
+        (sa, len)
+    } }
+
+    #[inline]
+    unsafe fn from_c(sa: &sockaddr_any, len: libc::socklen_t) -> Result<Self, Error> {
+        match sa.sa.sa_family as libc::c_int {
+            libc::AF_UNIX => Ok(SockAddr::Unix(UnixAddr { sa: sa.un, len })),
+            libc::AF_INET => Ok(SockAddr::Inet(InetAddr {
+                addr: u32::from_be(sa.in4.sin_addr.s_addr).to_be_bytes(),
+                port: u16::from_be(sa.in4.sin_port),
+            })),
+            libc::AF_INET6 => Ok(SockAddr::Inet6(Inet6Addr {
+                addr: sa.in6.sin6_addr.s6_addr,
+                port: u16::from_be(sa.in6.sin6_port),
+                flowinfo: sa.in6.sin6_flowinfo,
+                scope_id: sa.in6.sin6_scope_id,
+            })),
+            _ => Err(Error::EAFNOSUPPORT),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+union sockaddr_any {
+    sa: libc::sockaddr,
+    un: libc::sockaddr_un,
+    in4: libc::sockaddr_in,
+    in6: libc::sockaddr_in6,
+}
+
+/// IPv4 socket address
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct InetAddr {
+    /** Host address, in network order */ pub addr: [u8; 4],
+    /** Port number                    */ pub port: u16,
+}
+
+/// IPv6 socket address
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Inet6Addr {
+    /** Host address, in network order */ pub addr: [u8; 16],
+    /** Port number                    */ pub port: u16,
+    /** Flow information               */ pub flowinfo: u32,
+    /** Scope ID                       */ pub scope_id: u32,
+}
+
+/// Unix-domain socket address
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UnixAddr {
+    sa: libc::sockaddr_un,
+    len: libc::socklen_t,
+}
+
+/// Offset of `sun_path` in `sockaddr_un`, after the family and, on some systems, a length
+#[inline]
+fn sun_path_offset() -> usize {
+    let sa: libc::sockaddr_un = unsafe { mem::zeroed() };
+    sa.sun_path.as_ptr() as usize - &sa as *const _ as usize
+}
+
+impl UnixAddr {
+    /// Make an address of the given filesystem path.
+    ///
+    /// Fails with [`ENAMETOOLONG`](../struct.Error.html#associatedconstant.ENAMETOOLONG)
+    /// if the path is too long to fit in an address.
+    #[inline]
+    pub fn new(path: &[u8]) -> Result<Self, Error> {
+        if path.contains(&0) { return Err(Error::EINVAL) }
+        Self::from_bytes(path, 1)
+    }
+
+    /// Make an address in the abstract namespace, which is not in the filesystem.
+    #[inline]
+    pub fn new_abstract(name: &[u8]) -> Result<Self, Error> {
+        let mut a = Self::from_bytes(name, 1)?;
+        unsafe { ptr::copy(a.sa.sun_path.as_ptr(), a.sa.sun_path.as_mut_ptr().add(1), name.len()) };
+        a.sa.sun_path[0] = 0;
+        Ok(a)
+    }
+
+    /// Make an unnamed address.
+    #[inline]
+    pub fn unnamed() -> Self {
+        let mut sa: libc::sockaddr_un = unsafe { mem::zeroed() };
+        sa.sun_family = libc::AF_UNIX as _;
+        UnixAddr { sa, len: sun_path_offset() as _ }
+    }
+
+    #[inline]
+    fn from_bytes(bs: &[u8], extra: usize) -> Result<Self, Error> {
+        let mut a = Self::unnamed();
+        if bs.len() + extra > a.sa.sun_path.len() { return Err(Error::ENAMETOOLONG) }
+        unsafe { ptr::copy_nonoverlapping(bs.as_ptr(), a.sa.sun_path.as_mut_ptr() as *mut u8, bs.len()) };
+        a.len = (sun_path_offset() + bs.len() + extra) as _;
+        Ok(a)
+    }
+
+    #[inline]
+    fn bytes(&self) -> &[u8] {
+        let l = cmp::min(self.len as usize, mem::size_of::<libc::sockaddr_un>()).saturating_sub(sun_path_offset());
+        unsafe { slice::from_raw_parts(self.sa.sun_path.as_ptr() as *const u8, l) }
+    }
+
+    /// Return the filesystem path of the address, if it has one.
+    #[inline]
+    pub fn path(&self) -> Option<&[u8]> {
+        let bs = self.bytes();
+        match bs.first() {
+            None | Some(0) => None,
+            Some(_) => Some(&bs[..bs.iter().position(|&b| 0 == b).unwrap_or(bs.len())]),
+        }
+    }
+
+    /// Return the abstract name of the address, if it has one.
+    #[inline]
+    pub fn abstract_name(&self) -> Option<&[u8]> {
+        let bs = self.bytes();
+        match bs.first() {
+            Some(0) => Some(&bs[1..]),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Debug for UnixAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(path) = self.path() { f.debug_tuple("Path").field(&path).finish() }
+        else if let Some(name) = self.abstract_name() { f.debug_tuple("Abstract").field(&name).finish() }
+        else { f.write_str("Unnamed") }
+    }
+}
+
+/// Make a new socket in the given `domain`, of the given `typ`e.
+///
+/// `protocol` is conventionally 0, meaning the sole protocol of that domain and type.
+/// Only `O_CLOEXEC` and `O_NONBLOCK` of `flags` are meaningful.
+#[inline]
+pub fn socket(domain: Domain, typ: SockType, protocol: libc::c_int, flags: OpenFlags) -> Result<File, Error> {
+    unsafe { esyscall!(SOCKET, domain.0, typ.0 | sock_flags(flags), protocol) }
+        .map(|fd| File::new_unchecked(fd as _))
+}
+
+/// Return a new pair of connected sockets.
+#[inline]
+pub fn socket_pair(domain: Domain, typ: SockType, protocol: libc::c_int, flags: OpenFlags) -> Result<(File, File), Error> { unsafe {
+    let mut fds = MU::<[libc::c_int; 2]>::uninit().assume_init();
+    esyscall!(SOCKETPAIR, domain.0, typ.0 | sock_flags(flags), protocol, &mut fds as *mut _)?;
+    Ok((File::new_unchecked(fds[0] as _), File::new_unchecked(fds[1] as _)))
+} }
+
+/// Bind the socket to the given address.
+#[inline]
+pub fn bind(sock: &File, addr: &SockAddr) -> Result<(), Error> {
+    let (sa, len) = addr.to_c();
+    unsafe { esyscall_!(BIND, sock.fd(), &sa as *const _, len) }
+}
+
+/// Mark the socket as accepting connections, with at most `backlog` connections pending.
+#[inline]
+pub fn listen(sock: &File, backlog: usize) -> Result<(), Error> {
+    unsafe { esyscall_!(LISTEN, sock.fd(), backlog) }
+}
+
+/// Accept a connection on the socket, and return a new socket of it and the address of the peer,
+/// or `None` rather than the address if it is of none of the domains of `SockAddr`.
+///
+/// Only `O_CLOEXEC` and `O_NONBLOCK` of `flags` are meaningful.
+#[inline]
+pub fn accept(sock: &File, flags: OpenFlags) -> Result<(File, Option<SockAddr>), Error> { unsafe {
+    let mut sa = MU::<sockaddr_any>::zeroed();
+    let mut len = mem::size_of::<sockaddr_any>() as libc::socklen_t;
+    let fd = esyscall!(ACCEPT4, sock.fd(), sa.as_mut_ptr(), &mut len as *mut _, sock_flags(flags))?;
+    Ok((File::new_unchecked(fd as _), SockAddr::from_c(&sa.assume_init(), len).ok()))
+} }
+
+/// Connect the socket to the given address.
+#[inline]
+pub fn connect(sock: &File, addr: &SockAddr) -> Result<(), Error> {
+    let (sa, len) = addr.to_c();
+    unsafe { esyscall_!(CONNECT, sock.fd(), &sa as *const _, len) }
+}
+
+/// Which directions of a connection to shut down
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Shutdown {
+    /** Further receptions    */ Read,
+    /** Further transmissions */ Write,
+    /** Both                  */ Both,
+}
+
+/// Shut down part or all of a connection.
+#[inline]
+pub fn shutdown(sock: &File, how: Shutdown) -> Result<(), Error> {
+    unsafe { esyscall_!(SHUTDOWN, sock.fd(), match how {
+        Shutdown::Read  => libc::SHUT_RD,
+        Shutdown::Write => libc::SHUT_WR,
+        Shutdown::Both  => libc::SHUT_RDWR,
+    }) }
+}
+
+/// Return the address to which the socket is bound.
+#[inline]
+pub fn sock_name(sock: &File) -> Result<SockAddr, Error> { unsafe {
+    let mut sa = MU::<sockaddr_any>::zeroed();
+    let mut len = mem::size_of::<sockaddr_any>() as libc::socklen_t;
+    esyscall!(GETSOCKNAME, sock.fd(), sa.as_mut_ptr(), &mut len as *mut _)?;
+    SockAddr::from_c(&sa.assume_init(), len)
+} }
+
+/// Return the address of the peer to which the socket is connected.
+#[inline]
+pub fn peer_name(sock: &File) -> Result<SockAddr, Error> { unsafe {
+    let mut sa = MU::<sockaddr_any>::zeroed();
+    let mut len = mem::size_of::<sockaddr_any>() as libc::socklen_t;
+    esyscall!(GETPEERNAME, sock.fd(), sa.as_mut_ptr(), &mut len as *mut _)?;
+    SockAddr::from_c(&sa.assume_init(), len)
+} }
+
+/// Translate `O_CLOEXEC` and `O_NONBLOCK` of `flags` into the `SOCK_*` flags, whose values differ on some systems.
+#[inline]
+fn sock_flags(flags: OpenFlags) -> libc::c_int {
+    (if flags.contains(OpenFlags::O_CLOEXEC)  { libc::SOCK_CLOEXEC  } else { 0 }) |
+    (if flags.contains(OpenFlags::O_NONBLOCK) { libc::SOCK_NONBLOCK } else { 0 })
+}
+
+#[cfg(test)]
+mod tests {
+    use io::{Read, Write};
+    use process;
+    use super::*;
+
+    fn echo(a: &mut File, b: &mut File) {
+        let mut buf = [0; 8];
+        assert_eq!(5, a.write(b"hello").unwrap());
+        assert_eq!(5, b.read(&mut buf).unwrap());
+        assert_eq!(b"hello", &buf[..5]);
+    }
+
+    #[test]
+    fn unix_pair() {
+        let (mut a, mut b) = socket_pair(Domain::Unix, SockType::Stream, 0, OpenFlags::O_CLOEXEC).unwrap();
+        echo(&mut a, &mut b);
+        echo(&mut b, &mut a);
+        assert!(a.is_cloexec().unwrap());
+        assert!(!a.is_nonblocking().unwrap());
+    }
+
+    #[test]
+    fn unix_abstract() {
+        let mut name = *b"unix-net-test-0000000000";
+        let mut pid = process::pid();
+        for b in name.iter_mut().rev().take(10) { *b = b'0' + (pid % 10) as u8; pid /= 10 }
+        let addr = SockAddr::Unix(UnixAddr::new_abstract(&name).unwrap());
+
+        let l = socket(Domain::Unix, SockType::Stream, 0, OpenFlags::O_CLOEXEC).unwrap();
+        bind(&l, &addr).unwrap();
+        listen(&l, 1).unwrap();
+        assert_eq!(addr, sock_name(&l).unwrap());
+
+        let mut c = socket(Domain::Unix, SockType::Stream, 0, OpenFlags::O_CLOEXEC).unwrap();
+        connect(&c, &addr).unwrap();
+        let (mut a, peer) = accept(&l, OpenFlags::O_CLOEXEC | OpenFlags::O_NONBLOCK).unwrap();
+        assert_eq!(Some(SockAddr::Unix(UnixAddr::unnamed())), peer);
+        assert_eq!(addr, peer_name(&c).unwrap());
+        assert!(a.is_nonblocking().unwrap());
+        echo(&mut c, &mut a);
+        echo(&mut a, &mut c);
+
+        match sock_name(&l).unwrap() {
+            SockAddr::Unix(a) => {
+                assert_eq!(Some(&name[..]), a.abstract_name());
+                assert_eq!(None, a.path());
+            },
+            a => panic!("{:?}", a),
+        }
+    }
+
+    #[test]
+    fn unix_path() {
+        let a = UnixAddr::new(b"/tmp/sock").unwrap();
+        assert_eq!(Some(&b"/tmp/sock"[..]), a.path());
+        assert_eq!(None, a.abstract_name());
+        assert_eq!(Err(Error::ENAMETOOLONG), UnixAddr::new(&[b'a'; 0x100]));
+    }
+
+    #[test]
+    fn inet_loopback() {
+        let l = socket(Domain::Inet, SockType::Stream, 0, OpenFlags::O_CLOEXEC).unwrap();
+        bind(&l, &SockAddr::Inet(InetAddr { addr: [127, 0, 0, 1], port: 0 })).unwrap();
+        listen(&l, 1).unwrap();
+        let addr = sock_name(&l).unwrap();
+        match addr {
+            SockAddr::Inet(a) => { assert_eq!([127, 0, 0, 1], a.addr); assert_ne!(0, a.port) },
+            a => panic!("{:?}", a),
+        }
+
+        let mut c = socket(Domain::Inet, SockType::Stream, 0, OpenFlags::O_CLOEXEC).unwrap();
+        connect(&c, &addr).unwrap();
+        let (mut a, peer) = accept(&l, OpenFlags::O_CLOEXEC).unwrap();
+        assert_eq!(Some(sock_name(&c).unwrap()), peer);
+        assert_eq!(addr, peer_name(&c).unwrap());
+        echo(&mut c, &mut a);
+        shutdown(&a, Shutdown::Write).unwrap();
+        assert_eq!(0, c.read(&mut [0; 1]).unwrap());
+    }
+
+    #[test]
+    fn inet_udp() {
+        let loopback = SockAddr::Inet(InetAddr { addr: [127, 0, 0, 1], port: 0 });
+        let mut a = socket(Domain::Inet, SockType::Dgram, 0, OpenFlags::O_CLOEXEC).unwrap();
+        let mut b = socket(Domain::Inet, SockType::Dgram, 0, OpenFlags::O_CLOEXEC).unwrap();
+        bind(&a, &loopback).unwrap();
+        bind(&b, &loopback).unwrap();
+        connect(&a, &sock_name(&b).unwrap()).unwrap();
+        connect(&b, &sock_name(&a).unwrap()).unwrap();
+        echo(&mut a, &mut b);
+    }
+}
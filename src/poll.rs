@@ -52,3 +52,116 @@ impl PollExt for [Poll] {
         unsafe { esyscall!(POLL, self.as_ptr(), self.len(), t.as_ref().map_or(::core::ptr::null(), |p| p as *const _), 0) }
     }
 }
+
+/// Set of file descriptors to poll, kept in the system
+///
+/// Unlike `PollExt::poll`, the set is not submitted anew on each wait, so waiting is cheap however many file descriptors are in it.
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+pub struct Epoll(File);
+
+#[cfg(target_os = "linux")]
+bitflags! {
+    /// How to poll a file descriptor in an `Epoll`
+    pub struct EpollMode: u32 {
+        /// Report readiness only when it changes, rather than so long as the file descriptor is ready.
+        const EdgeTriggered = ::libc::EPOLLET      as u32;
+        /// Report readiness once, and then disable the file descriptor until it is modified.
+        const OneShot       = ::libc::EPOLLONESHOT as u32;
+    }
+}
+
+/// Ready file descriptor, as reported by `Epoll::wait`
+#[cfg(target_os = "linux")]
+#[derive(Clone, Copy)]
+#[cfg_attr(target_arch = "x86_64", repr(C, packed))]
+#[cfg_attr(not(target_arch = "x86_64"), repr(C))]
+pub struct EpollEvent {
+    ev: u32,
+    token: u64,
+}
+
+#[cfg(target_os = "linux")]
+impl EpollEvent {
+    /// Return which events are ready on the file descriptor.
+    #[inline]
+    pub fn ready(self) -> Event { Event::from_bits_truncate(self.ev as _) }
+
+    /// Return the token given when the file descriptor was added.
+    #[inline]
+    pub fn token(self) -> u64 { self.token }
+}
+
+#[cfg(target_os = "linux")]
+impl Default for EpollEvent {
+    #[inline]
+    fn default() -> Self { EpollEvent { ev: 0, token: 0 } }
+}
+
+#[cfg(target_os = "linux")]
+impl ::core::fmt::Debug for EpollEvent {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        f.debug_struct("EpollEvent").field("ready", &self.ready()).field("token", &self.token()).finish()
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Epoll {
+    /// Make a new, empty `Epoll`.
+    ///
+    /// Only `O_CLOEXEC` of `flags` is meaningful.
+    #[inline]
+    pub fn new(flags: OpenFlags) -> Result<Self, Error> {
+        unsafe { esyscall!(EPOLL_CREATE1, (flags & OpenFlags::O_CLOEXEC).bits()) }
+            .map(|fd| Epoll(File::new_unchecked(fd as _)))
+    }
+
+    /// Add `f` to the set, to poll for `ev`; `token` will be reported when it is ready.
+    #[inline]
+    pub fn add(&self, f: &File, ev: Event, mode: EpollMode, token: u64) -> Result<(), Error> {
+        self.ctl(::libc::EPOLL_CTL_ADD, f, ev, mode, token)
+    }
+
+    /// Modify what to poll for on `f`, which must already be in the set.
+    #[inline]
+    pub fn modify(&self, f: &File, ev: Event, mode: EpollMode, token: u64) -> Result<(), Error> {
+        self.ctl(::libc::EPOLL_CTL_MOD, f, ev, mode, token)
+    }
+
+    /// Delete `f` from the set.
+    #[inline]
+    pub fn delete(&self, f: &File) -> Result<(), Error> {
+        self.ctl(::libc::EPOLL_CTL_DEL, f, Event::empty(), EpollMode::empty(), 0)
+    }
+
+    #[inline]
+    fn ctl(&self, op: ::libc::c_int, f: &File, ev: Event, mode: EpollMode, token: u64) -> Result<(), Error> {
+        let e = EpollEvent { ev: ev.bits() as u16 as u32 | mode.bits(), token };
+        unsafe { esyscall_!(EPOLL_CTL, self.0.fd(), op, f.fd(), &e as *const _) }
+    }
+
+    /// Wait until some file descriptor in the set is ready, or the timeout `t` elapses, and return the ready ones.
+    ///
+    /// At most `evs.len()` are returned; the others are reported on later calls.
+    #[inline]
+    pub fn wait<'a>(&self, evs: &'a mut [EpollEvent], t: Option<Span>) -> Result<&'a [EpollEvent], Error> {
+        let t = match t {
+            None => -1,
+            Some(t) => {
+                let ms = (t.to_ns() + 999_999) / 1_000_000;
+                if ms > ::libc::c_int::max_value() as i128 { return Err(Error::ERANGE) }
+                ::core::cmp::max(ms, 0) as ::libc::c_int
+            },
+        };
+        let n = unsafe { esyscall!(EPOLL_PWAIT, self.0.fd(), evs.as_mut_ptr(), evs.len(), t,
+                                   ::core::ptr::null::<u64>(), 8)? };
+        Ok(&evs[..n])
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl ::core::ops::Deref for Epoll {
+    type Target = File;
+    #[inline]
+    fn deref(&self) -> &File { &self.0 }
+}
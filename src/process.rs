@@ -1,9 +1,18 @@
 //! Process operations
 
 use core::mem::MaybeUninit as MU;
+use tempus::Span;
+#[cfg(target_os = "linux")]
+use io::{Read, Write};
+use null_terminated::Nul;
 pub use libc::id_t as Id;
 
-use Error;
+use {Error, File, Str};
+#[cfg(target_os = "linux")]
+use file::{AtFlags, O_CLOEXEC, OpenMode, Perm, exec_at, new_pipe, open_at};
+#[cfg(target_os = "linux")]
+use mem::{Map, MapExt};
+use signal::Signal;
 
 /// Create a new process which is a copy of the calling process.
 #[inline]
@@ -38,18 +47,10 @@ pub enum WaitSpec {
 impl WaitSpec {
     /// Wait for the state of a child process to change, and return information about it.
     #[inline]
-    pub fn wait(self, flags: WaitFlags) -> Result<(WaitInfo, ::libc::rusage), Error> {
+    pub fn wait(self, flags: WaitFlags) -> Result<(WaitInfo, Rusage), Error> {
         unsafe {
             let (id_type, id) = self.to_wait_args();
-            let mut si = siginfo_ { u: () };
-            let mut ru = MU::<::libc::rusage>::uninit();
-            si.si.si_pid = 0;
-            #[cfg(target_os = "linux")]
-            esyscall!(WAITID, id_type, id, &mut si as *mut _, flags.bits(), ru.as_mut_ptr())?;
-            #[cfg(target_os = "freebsd")]
-            esyscall!(WAIT6, id_type, id, &mut 0usize as *mut _, flags.bits(), ru.as_mut_ptr(), &mut si as *mut _)?;
-            if 0 == si.si.si_pid { return Err(Error::EWOULDBLOCK) }
-            Ok((WaitInfo::from_c(si.si), ru.assume_init()))
+            wait_raw(id_type, id, flags)
         }
     }
 
@@ -64,6 +65,25 @@ impl WaitSpec {
     }
 }
 
+#[inline]
+unsafe fn wait_raw(id_type: ::libc::idtype_t, id: Id, flags: WaitFlags) -> Result<(WaitInfo, Rusage), Error> {
+    let mut si = siginfo_ { u: () };
+    let mut ru = MU::<::libc::rusage>::uninit();
+    si.si.si_pid = 0;
+    #[cfg(target_os = "linux")]
+    esyscall!(WAITID, id_type, id, &mut si as *mut _, flags.bits(), ru.as_mut_ptr())?;
+    #[cfg(target_os = "freebsd")]
+    esyscall!(WAIT6, id_type, id, &mut 0usize as *mut _, flags.bits(), ru.as_mut_ptr(), &mut si as *mut _)?;
+    if 0 == si.si.si_pid { return Err(Error::EWOULDBLOCK) }
+    Ok((WaitInfo::from_c(si.si), Rusage::from(ru.assume_init())))
+}
+
+#[cfg(target_os = "linux")]
+const P_PIDFD: ::libc::idtype_t = 3;
+// Never used, as we have no process file descriptors.
+#[cfg(not(target_os = "linux"))]
+const P_PIDFD: ::libc::idtype_t = ::libc::P_PID;
+
 #[allow(missing_docs)]
 mod wait_flags { bitflags! {
     pub struct WaitFlags: usize {
@@ -97,6 +117,87 @@ impl WaitInfo {
     }
 }
 
+impl WaitInfo {
+    /// Decode the cause and status of the change of state.
+    #[inline]
+    pub fn exit_status(self) -> ExitStatus { ExitStatus::from(self) }
+}
+
+/// How a child process changed state
+///
+/// A signal is `None` if the system reported a number out of range of `Signal`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ExitStatus {
+    /** Exited with the given code             */ Exited(isize),
+    /// Killed by a signal
+    Signaled {
+        /** Signal which killed the process     */ signal: Option<Signal>,
+        /** Whether a core dump was made        */ core_dumped: bool,
+    },
+    /** Stopped by the given signal            */ Stopped(Option<Signal>),
+    /** Continued by `SIGCONT`                  */ Continued,
+    /** Trapped, when traced, by the given signal */ Trapped(Option<Signal>),
+    /// Changed state in a way not known here
+    Other {
+        /** Cause, as the system reported it    */ code: WaitCode,
+        /** Status, as the system reported it   */ status: isize,
+    },
+}
+
+impl From<WaitInfo> for ExitStatus {
+    #[inline]
+    fn from(wi: WaitInfo) -> Self {
+        let sig = Signal::new(wi.status as _);
+        match wi.code {
+            WaitCode::Exit => ExitStatus::Exited(wi.status),
+            WaitCode::Kill => ExitStatus::Signaled { signal: sig, core_dumped: false },
+            WaitCode::Dump => ExitStatus::Signaled { signal: sig, core_dumped: true },
+            WaitCode::Stop => ExitStatus::Stopped(sig),
+            WaitCode::Cont => ExitStatus::Continued,
+            WaitCode::Trap => ExitStatus::Trapped(sig),
+            code           => ExitStatus::Other { code, status: wi.status },
+        }
+    }
+}
+
+impl ExitStatus {
+    /// Return whether the process exited with code 0.
+    #[inline]
+    pub fn success(self) -> bool { ExitStatus::Exited(0) == self }
+}
+
+/// Resource usage
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rusage {
+    /** User CPU time                             */ pub utime: Span,
+    /** System CPU time                           */ pub stime: Span,
+    /** Maximum resident set size, in kibibytes   */ pub maxrss: usize,
+    /** Page faults serviced without I/O          */ pub minflt: usize,
+    /** Page faults serviced with I/O             */ pub majflt: usize,
+    /** Block input operations                    */ pub inblock: usize,
+    /** Block output operations                   */ pub oublock: usize,
+    /** Voluntary context switches                */ pub nvcsw: usize,
+    /** Involuntary context switches              */ pub nivcsw: usize,
+}
+
+impl From<::libc::rusage> for Rusage {
+    #[inline]
+    fn from(ru: ::libc::rusage) -> Self {
+        let span = |tv: ::libc::timeval| Span::from_ns(tv.tv_sec as i128 * 1_000_000_000 + tv.tv_usec as i128 * 1_000);
+        Rusage {
+            utime: span(ru.ru_utime),
+            stime: span(ru.ru_stime),
+            maxrss: ru.ru_maxrss as _,
+            minflt: ru.ru_minflt as _,
+            majflt: ru.ru_majflt as _,
+            inblock: ru.ru_inblock as _,
+            oublock: ru.ru_oublock as _,
+            nvcsw: ru.ru_nvcsw as _,
+            nivcsw: ru.ru_nivcsw as _,
+        }
+    }
+}
+
 /// Cause of process termination
 #[repr(transparent)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -119,9 +220,12 @@ struct siginfo {
     si_signo:  ::libc::c_int,
     si_errno:  ::libc::c_int,
     si_code:   ::libc::c_int,
+    // The union of the other fields is pointer-aligned.
+    #[cfg(target_pointer_width = "64")]
+    _pad:      ::libc::c_int,
+    // The fields for `SIGCHLD`
     si_pid:    ::libc::pid_t,
     si_uid:    ::libc::uid_t,
-    si_value:  ::libc::sigval,
     si_status: ::libc::c_int,
 }
 
@@ -134,3 +238,348 @@ union siginfo_ {
     pad: [u8; 0x80],
     u: (),
 }
+
+/// Where to connect a standard stream of a child process
+#[derive(Debug)]
+pub enum Stdio<'a> {
+    /** Inherit the stream of the calling process                                 */ Inherit,
+    /** Connect the stream to the given file                                      */ File(&'a File),
+    /** Connect the stream to a new pipe, the other end of which is in the `Child` */ Pipe,
+    /** Connect the stream to `/dev/null`                                         */ Null,
+}
+
+/// Specification of a child process to spawn
+///
+/// Failures in the child before the program is executed, e.g. the program file not being found,
+/// are sent back to the parent and returned from `spawn`.
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+pub struct Command<'a> {
+    opt_dir: Option<&'a File>,
+    path: &'a Str,
+    argv: &'a Nul<&'a Str>,
+    env_clear: bool,
+    env_remove: &'a [&'a [u8]],
+    env_add: &'a [&'a Str],
+    cwd: Option<&'a File>,
+    stdio: [Stdio<'a>; 3],
+    keep: &'a [&'a File],
+}
+
+#[cfg(target_os = "linux")]
+impl<'a> Command<'a> {
+    /// Specify to execute the program file at `path`, with the argument strings `argv`.
+    ///
+    /// By default, the child inherits the environment, working directory, and standard streams of the calling process.
+    #[inline]
+    pub fn new(path: &'a Str, argv: &'a Nul<&'a Str>) -> Self {
+        Command {
+            opt_dir: None, path, argv,
+            env_clear: false, env_remove: &[], env_add: &[],
+            cwd: None,
+            stdio: [Stdio::Inherit, Stdio::Inherit, Stdio::Inherit],
+            keep: &[],
+        }
+    }
+
+    /// Look up the program path relative to `opt_dir`, as in `exec_at`.
+    #[inline]
+    pub fn at(&mut self, opt_dir: Option<&'a File>) -> &mut Self { self.opt_dir = opt_dir; self }
+
+    /// Start the environment empty rather than a copy of `environ`.
+    #[inline]
+    pub fn env_clear(&mut self) -> &mut Self { self.env_clear = true; self }
+
+    /// Remove the variables of the given names from the environment.
+    #[inline]
+    pub fn env_remove(&mut self, keys: &'a [&'a [u8]]) -> &mut Self { self.env_remove = keys; self }
+
+    /// Add the given entries, of form "key=value", to the environment, replacing any of the same names.
+    #[inline]
+    pub fn envs(&mut self, entries: &'a [&'a Str]) -> &mut Self { self.env_add = entries; self }
+
+    /// Set the working directory of the child.
+    #[inline]
+    pub fn cwd(&mut self, dir: &'a File) -> &mut Self { self.cwd = Some(dir); self }
+
+    /// Set where to connect the standard input of the child.
+    #[inline]
+    pub fn stdin(&mut self, s: Stdio<'a>) -> &mut Self { self.stdio[0] = s; self }
+
+    /// Set where to connect the standard output of the child.
+    #[inline]
+    pub fn stdout(&mut self, s: Stdio<'a>) -> &mut Self { self.stdio[1] = s; self }
+
+    /// Set where to connect the standard error of the child.
+    #[inline]
+    pub fn stderr(&mut self, s: Stdio<'a>) -> &mut Self { self.stdio[2] = s; self }
+
+    /// Let the child inherit the given files, even if they are marked close-on-exec.
+    ///
+    /// Other files are inherited only if they are not marked close-on-exec.
+    #[inline]
+    pub fn keep(&mut self, files: &'a [&'a File]) -> &mut Self { self.keep = files; self }
+
+    /// Spawn the child process.
+    ///
+    /// The child is made with `CLONE_VFORK`, so this returns only once it executed the program or failed.
+    /// `CLONE_VM` is not used, as the child runs code between the clone and the exec, which is unsound in a shared address space.
+    pub fn spawn(&self) -> Result<Child, Error> {
+        let env_map = self.make_env()?;
+        let envp: &Nul<&Str> = match env_map {
+            None => unsafe { ::env_::environ.0 },
+            Some(ref m) => unsafe { Nul::new_unchecked(m.as_ptr() as *const &Str) },
+        };
+
+        let mut child_fds = [None; 3];
+        let mut parent_ends = [None, None, None];
+        let mut holders = [None, None, None];
+        for (k, s) in self.stdio.iter().enumerate() {
+            match *s {
+                Stdio::Inherit => (),
+                Stdio::File(f) => child_fds[k] = Some(f.fd()),
+                Stdio::Pipe => {
+                    let (rx, tx) = new_pipe(O_CLOEXEC)?;
+                    let (c, p) = if 0 == k { (rx, tx) } else { (tx, rx) };
+                    child_fds[k] = Some(c.fd());
+                    holders[k] = Some(c);
+                    parent_ends[k] = Some(p);
+                },
+                Stdio::Null => {
+                    let f = open_at(None, str0!("/dev/null"), OpenMode::RdWr | O_CLOEXEC, None)?;
+                    child_fds[k] = Some(f.fd());
+                    holders[k] = Some(f);
+                },
+            }
+        }
+
+        let (mut err_rx, err_tx) = new_pipe(O_CLOEXEC)?;
+        let mut pidfd: ::libc::c_int = -1;
+        let pid = unsafe { esyscall!(CLONE, ::libc::CLONE_VFORK | CLONE_PIDFD | ::libc::SIGCHLD, 0,
+                                     &mut pidfd as *mut _, 0, 0)? } as Id;
+        if 0 == pid {
+            let e = unsafe { self.exec_child(&child_fds, envp) };
+            let mut err_tx = err_tx;
+            let _ = err_tx.write_all(&e.0.get().to_ne_bytes());
+            quit(127);
+        }
+        drop((err_tx, holders));
+        // Kernels before 5.2 ignore `CLONE_PIDFD`.
+        // The child is already running, so if we can not open a pidfd either, refer to it by its PID alone rather than fail.
+        let pidfd = if pidfd >= 0 { Some(File::new_unchecked(pidfd as _)) } else { open_pidfd(pid).unwrap_or(None) };
+
+        let [stdin, stdout, stderr] = parent_ends;
+        let child = Child { pid, pidfd, stdin, stdout, stderr };
+
+        let mut buf = [0; ::core::mem::size_of::<usize>()];
+        let n = loop {
+            match err_rx.try_read_full(&mut buf) {
+                Err((Error::EINTR, 0)) => continue,
+                // We can not know whether the program is running, and the caller can not reach it, so end it.
+                Err((e, _)) => {
+                    let _ = child.kill(Signal::SIGKILL);
+                    let _ = child.wait();
+                    return Err(e);
+                },
+                Ok(n) => break n,
+            }
+        };
+        if 0 != n {
+            let _ = child.wait();
+            return Err(::core::num::NonZeroUsize::new(usize::from_ne_bytes(buf)).map_or(Error::EIO, Error::from));
+        }
+        Ok(child)
+    }
+
+    #[inline]
+    unsafe fn exec_child(&self, child_fds: &[Option<isize>; 3], envp: &Nul<&Str>) -> Error {
+        if let Some(dir) = self.cwd {
+            if let Err(e) = esyscall_!(FCHDIR, dir.fd()) { return e }
+        }
+        // Move any source among 0, 1, and 2 out of the way first, lest an earlier `dup2` overwrite it.
+        let mut child_fds = *child_fds;
+        for (k, opt_fd) in child_fds.iter_mut().enumerate() {
+            match *opt_fd {
+                Some(fd) if fd <= 2 && fd != k as isize => match esyscall!(FCNTL, fd, ::libc::F_DUPFD_CLOEXEC, 3) {
+                    Ok(new_fd) => *opt_fd = Some(new_fd as _),
+                    Err(e) => return e,
+                },
+                _ => (),
+            }
+        }
+        for (k, &opt_fd) in child_fds.iter().enumerate() {
+            let r = match opt_fd {
+                None => Ok(()),
+                Some(fd) if fd == k as isize => esyscall_!(FCNTL, fd, ::libc::F_SETFD, 0),
+                Some(fd) => esyscall_!(DUP2, fd, k),
+            };
+            if let Err(e) = r { return e }
+        }
+        for f in self.keep {
+            if let Err(e) = esyscall_!(FCNTL, f.fd(), ::libc::F_SETFD, 0) { return e }
+        }
+        match exec_at(self.opt_dir, self.path, self.argv, envp, AtFlags::Follow) {
+            Ok(v) => ::void::unreachable(v),
+            Err(e) => e,
+        }
+    }
+
+    /// Make the environment of the child, or return `None` to inherit ours unmodified.
+    fn make_env(&self) -> Result<Option<Map>, Error> {
+        if !self.env_clear && self.env_remove.is_empty() && self.env_add.is_empty() { return Ok(None) }
+        let env = unsafe { ::env_::environ.0 };
+        let n_env = if self.env_clear { 0 } else { env.iter().count() };
+        let ptr_size = ::core::mem::size_of::<&Str>();
+        let mut m = ().map(Perm::Read | Perm::Write, (n_env + self.env_add.len() + 1) * ptr_size)?;
+        let mut k = 0;
+        {
+            let mut put = |s: &Str| {
+                m[k*ptr_size..(k+1)*ptr_size].copy_from_slice(&(s.as_ptr() as usize).to_ne_bytes());
+                k += 1;
+            };
+            for &s in env.iter().take(n_env) {
+                let key = env_key(s);
+                if self.env_remove.iter().any(|&r| r == key) ||
+                   self.env_add.iter().any(|&a| env_key(a) == key) { continue }
+                put(s);
+            }
+            for &s in self.env_add { put(s) }
+        }
+        m[k*ptr_size..(k+1)*ptr_size].copy_from_slice(&0usize.to_ne_bytes());
+        Ok(Some(m))
+    }
+}
+
+#[inline]
+fn env_key(s: &Str) -> &[u8] {
+    let bs = &s[..];
+    &bs[..bs.iter().position(|&b| b'=' == b).unwrap_or(bs.len())]
+}
+
+/// Child process
+///
+/// Where the system supports it, the child is referred to by a process file descriptor as well as its ID,
+/// so it can not be confused with another process which happens to reuse the ID after it terminates.
+#[derive(Debug)]
+pub struct Child {
+    pid: Id,
+    pidfd: Option<File>,
+    /** Our end of the pipe to the standard input of the child, if any   */ pub stdin: Option<File>,
+    /** Our end of the pipe from the standard output of the child, if any */ pub stdout: Option<File>,
+    /** Our end of the pipe from the standard error of the child, if any  */ pub stderr: Option<File>,
+}
+
+#[cfg(target_os = "linux")]
+const CLONE_PIDFD: ::libc::c_int = 0x1000;
+
+#[cfg(target_os = "linux")]
+#[inline]
+fn open_pidfd(pid: Id) -> Result<Option<File>, Error> {
+    match unsafe { esyscall_nr!(::util::nr::PIDFD_OPEN, pid, 0) } {
+        Ok(fd) => Ok(Some(File::new_unchecked(fd as _))),
+        Err(Error::ENOSYS) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+impl Child {
+    /// Make a `Child` of the child process `pid`, e.g. as returned from `fork`.
+    ///
+    /// The process must be a child of the calling one, and not yet waited for.
+    #[inline]
+    pub fn new(pid: Id) -> Result<Self, Error> {
+        #[cfg(target_os = "linux")]
+        let pidfd = open_pidfd(pid)?;
+        #[cfg(not(target_os = "linux"))]
+        let pidfd = None;
+        Ok(Child { pid, pidfd, stdin: None, stdout: None, stderr: None })
+    }
+
+    /// Return the ID of the child.
+    #[inline]
+    pub fn id(&self) -> Id { self.pid }
+
+    /// Return the process file descriptor of the child, if the system supports them.
+    ///
+    /// It is readable once the child terminates, so it can be polled.
+    #[inline]
+    pub fn pidfd(&self) -> Option<&File> { self.pidfd.as_ref() }
+
+    /// Wait for the child to terminate, and return information about it.
+    #[inline]
+    pub fn wait(&self) -> Result<(WaitInfo, Rusage), Error> { self.wait_flags(WaitFlags::Exit) }
+
+    /// Return information about the child if it terminated, else `None`, without waiting.
+    #[inline]
+    pub fn try_wait(&self) -> Result<Option<(WaitInfo, Rusage)>, Error> {
+        match self.wait_flags(WaitFlags::Exit | WaitFlags::NoHang) {
+            Ok(x) => Ok(Some(x)),
+            Err(Error::EWOULDBLOCK) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    #[inline]
+    fn wait_flags(&self, flags: WaitFlags) -> Result<(WaitInfo, Rusage), Error> {
+        if let Some(ref f) = self.pidfd {
+            // Kernels before 5.4 lack `P_PIDFD`.
+            match unsafe { wait_raw(P_PIDFD, f.fd() as _, flags) } {
+                Err(Error::EINVAL) => (),
+                r => return r,
+            }
+        }
+        WaitSpec::Pid(self.pid).wait(flags)
+    }
+
+    /// Send `sig` to the child.
+    #[cfg(target_os = "linux")]
+    #[inline]
+    pub fn kill(&self, sig: Signal) -> Result<(), Error> {
+        if let Some(ref f) = self.pidfd {
+            match unsafe { esyscall_nr!(::util::nr::PIDFD_SEND_SIGNAL, f.fd(), sig.to_c(),
+                                        ::core::ptr::null::<u8>(), 0) } {
+                Err(Error::ENOSYS) => (),
+                r => return r.map(|_| ()),
+            }
+        }
+        ::signal::kill(self.pid, sig)
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    fn sh(script: &Str, envs: &[&Str]) -> ExitStatus {
+        let argv = [Some(str0!("sh")), Some(str0!("-c")), Some(script), None];
+        let argv = unsafe { Nul::new_unchecked(argv.as_ptr() as *const &Str) };
+        let child = Command::new(str0!("/bin/sh"), argv).envs(envs).spawn().unwrap();
+        child.wait().unwrap().0.exit_status()
+    }
+
+    #[test]
+    fn exit_status() {
+        assert_eq!(ExitStatus::Exited(0), sh(str0!("exit 0"), &[]));
+        assert_eq!(ExitStatus::Exited(3), sh(str0!("exit 3"), &[]));
+        assert_eq!(ExitStatus::Signaled { signal: Some(Signal::SIGKILL), core_dumped: false },
+                   sh(str0!("kill -9 $$"), &[]));
+    }
+
+    #[test]
+    fn env() {
+        assert_eq!(ExitStatus::Exited(0), sh(str0!("test \"$UNIX_TEST_VAR\" = a=b"), &[str0!("UNIX_TEST_VAR=a=b")]));
+        assert_eq!(ExitStatus::Exited(0), sh(str0!("test -z \"$HOME\""), &[str0!("HOME=")]));
+    }
+
+    #[test]
+    fn try_wait() {
+        let argv = [Some(str0!("sh")), Some(str0!("-c")), Some(str0!("read x")), None];
+        let argv = unsafe { Nul::new_unchecked(argv.as_ptr() as *const &Str) };
+        let child = Command::new(str0!("/bin/sh"), argv).stdin(Stdio::Pipe).spawn().unwrap();
+        assert!(child.try_wait().unwrap().is_none());
+        child.kill(Signal::SIGTERM).unwrap();
+        let (wi, _) = child.wait().unwrap();
+        assert_eq!(child.id(), wi.pid);
+        assert_eq!(ExitStatus::Signaled { signal: Some(Signal::SIGTERM), core_dumped: false }, wi.exit_status());
+    }
+}
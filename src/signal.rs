@@ -0,0 +1,481 @@
+//! Signal handling
+//!
+//! Actions are only supported on architectures where the kernel's `sigaction` has the common layout; e.g. on MIPS, it differs.
+
+use core::{fmt, mem, ops::*, ptr};
+use core::mem::MaybeUninit as MU;
+use io::*;
+use libc;
+
+use {Error, File};
+use file::OpenFlags;
+use process::Id;
+
+/// Signal number
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Signal(libc::c_int);
+
+#[allow(missing_docs)]
+impl Signal {
+    pub const SIGHUP   : Self = Signal(libc::SIGHUP);
+    pub const SIGINT   : Self = Signal(libc::SIGINT);
+    pub const SIGQUIT  : Self = Signal(libc::SIGQUIT);
+    pub const SIGILL   : Self = Signal(libc::SIGILL);
+    pub const SIGTRAP  : Self = Signal(libc::SIGTRAP);
+    pub const SIGABRT  : Self = Signal(libc::SIGABRT);
+    pub const SIGBUS   : Self = Signal(libc::SIGBUS);
+    pub const SIGFPE   : Self = Signal(libc::SIGFPE);
+    pub const SIGKILL  : Self = Signal(libc::SIGKILL);
+    pub const SIGUSR1  : Self = Signal(libc::SIGUSR1);
+    pub const SIGSEGV  : Self = Signal(libc::SIGSEGV);
+    pub const SIGUSR2  : Self = Signal(libc::SIGUSR2);
+    pub const SIGPIPE  : Self = Signal(libc::SIGPIPE);
+    pub const SIGALRM  : Self = Signal(libc::SIGALRM);
+    pub const SIGTERM  : Self = Signal(libc::SIGTERM);
+    pub const SIGCHLD  : Self = Signal(libc::SIGCHLD);
+    pub const SIGCONT  : Self = Signal(libc::SIGCONT);
+    pub const SIGSTOP  : Self = Signal(libc::SIGSTOP);
+    pub const SIGTSTP  : Self = Signal(libc::SIGTSTP);
+    pub const SIGTTIN  : Self = Signal(libc::SIGTTIN);
+    pub const SIGTTOU  : Self = Signal(libc::SIGTTOU);
+    pub const SIGURG   : Self = Signal(libc::SIGURG);
+    pub const SIGXCPU  : Self = Signal(libc::SIGXCPU);
+    pub const SIGXFSZ  : Self = Signal(libc::SIGXFSZ);
+    pub const SIGVTALRM: Self = Signal(libc::SIGVTALRM);
+    pub const SIGPROF  : Self = Signal(libc::SIGPROF);
+    pub const SIGWINCH : Self = Signal(libc::SIGWINCH);
+    pub const SIGIO    : Self = Signal(libc::SIGIO);
+    pub const SIGSYS   : Self = Signal(libc::SIGSYS);
+}
+
+/// Least real-time signal number
+///
+/// The system C library conventionally reserves the first few real-time signals for itself, so we skip them:
+/// glibc reserves 32 and 33, and musl 32 to 34.
+#[cfg(not(target_env = "musl"))]
+const SIGRTMIN: libc::c_int = 34;
+#[cfg(target_env = "musl")]
+const SIGRTMIN: libc::c_int = 35;
+/// Greatest signal number
+const SIGRTMAX: libc::c_int = 64;
+
+impl Signal {
+    /// Make a `Signal` of a signal number, which is checked to be in range.
+    #[inline]
+    pub fn new(n: libc::c_int) -> Option<Self> {
+        if 0 < n && n <= SIGRTMAX { Some(Signal(n)) } else { None }
+    }
+
+    /// Return the `n`th real-time signal, if there is one.
+    #[inline]
+    pub fn rt(n: usize) -> Option<Self> {
+        if n <= (SIGRTMAX - SIGRTMIN) as usize { Some(Signal(SIGRTMIN + n as libc::c_int)) }
+        else { None }
+    }
+
+    /// Return the signal number.
+    #[inline]
+    pub fn to_c(self) -> libc::c_int { self.0 }
+}
+
+/// Set of signals
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SigSet(u64);
+
+impl SigSet {
+    /// Return the set of no signals.
+    #[inline]
+    pub const fn empty() -> Self { SigSet(0) }
+
+    /// Return the set of all signals.
+    #[inline]
+    pub const fn all() -> Self { SigSet(!0) }
+
+    /// Return whether `sig` is in the set.
+    #[inline]
+    pub fn contains(self, sig: Signal) -> bool { 0 != self.0 & bit(sig) }
+
+    /// Insert `sig` into the set.
+    #[inline]
+    pub fn insert(&mut self, sig: Signal) { self.0 |= bit(sig) }
+
+    /// Remove `sig` from the set.
+    #[inline]
+    pub fn remove(&mut self, sig: Signal) { self.0 &= !bit(sig) }
+
+    /// Iterate over the signals in the set.
+    #[inline]
+    pub fn iter(self) -> impl Iterator<Item = Signal> {
+        (1..=SIGRTMAX).map(Signal).filter(move |&sig| self.contains(sig))
+    }
+}
+
+#[inline]
+fn bit(sig: Signal) -> u64 { 1 << (sig.0 - 1) }
+
+impl fmt::Debug for SigSet {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { f.debug_set().entries(self.iter()).finish() }
+}
+
+impl From<Signal> for SigSet {
+    #[inline]
+    fn from(sig: Signal) -> Self { SigSet(bit(sig)) }
+}
+
+impl BitOr<Signal> for SigSet {
+    type Output = Self;
+    #[inline]
+    fn bitor(self, sig: Signal) -> Self { SigSet(self.0 | bit(sig)) }
+}
+
+impl BitOr for SigSet {
+    type Output = Self;
+    #[inline]
+    fn bitor(self, other: Self) -> Self { SigSet(self.0 | other.0) }
+}
+
+impl BitAnd for SigSet {
+    type Output = Self;
+    #[inline]
+    fn bitand(self, other: Self) -> Self { SigSet(self.0 & other.0) }
+}
+
+impl Not for SigSet {
+    type Output = Self;
+    #[inline]
+    fn not(self) -> Self { SigSet(!self.0) }
+}
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86", target_arch = "x86_64", target_arch = "arm", target_arch = "aarch64",
+                                    target_arch = "riscv64", target_arch = "powerpc64", target_arch = "s390x")))]
+/// What to do on receipt of a signal
+#[derive(Debug, Clone, Copy)]
+pub enum Handler {
+    /** Take the default action of the signal */ Default,
+    /** Ignore the signal                      */ Ignore,
+    /** Call the function with the signal      */ Handler(extern "C" fn(Signal)),
+    /// Call the function with the signal, information about it, and the interrupted context.
+    /// `SigFlags::SigInfo` is implied.
+    Action(extern "C" fn(Signal, *const libc::siginfo_t, *mut libc::c_void)),
+}
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86", target_arch = "x86_64", target_arch = "arm", target_arch = "aarch64",
+                                    target_arch = "riscv64", target_arch = "powerpc64", target_arch = "s390x")))]
+bitflags! {
+    /// Flags modifying behavior of signal handling
+    pub struct SigFlags: u32 {
+        /// Restart interrupted system calls rather than fail with
+        /// [`EINTR`](../struct.Error.html#associatedconstant.EINTR).
+        const Restart   = libc::SA_RESTART   as u32;
+        /// Call the handler with information about the signal.
+        const SigInfo   = libc::SA_SIGINFO   as u32;
+        /// Do not block the signal while its handler runs.
+        const NoDefer   = libc::SA_NODEFER   as u32;
+        /// Reset the action to the default on entry to the handler.
+        const ResetHand = libc::SA_RESETHAND as u32;
+        /// Run the handler on the alternate signal stack.
+        const OnStack   = libc::SA_ONSTACK   as u32;
+        /// For `SIGCHLD`, do not notify when a child stops or continues.
+        const NoCldStop = libc::SA_NOCLDSTOP as u32;
+        /// For `SIGCHLD`, do not transform terminated children into zombies.
+        const NoCldWait = libc::SA_NOCLDWAIT as u32;
+    }
+}
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86", target_arch = "x86_64", target_arch = "arm", target_arch = "aarch64",
+                                    target_arch = "riscv64", target_arch = "powerpc64", target_arch = "s390x")))]
+/// Action to take on receipt of a signal
+#[derive(Debug, Clone, Copy)]
+pub struct Action {
+    /** What to do                                  */ pub handler: Handler,
+    /** Which further signals to block meanwhile    */ pub mask: SigSet,
+    /** Flags                                       */ pub flags: SigFlags,
+}
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86", target_arch = "x86_64", target_arch = "arm", target_arch = "aarch64",
+                                    target_arch = "riscv64", target_arch = "powerpc64", target_arch = "s390x")))]
+impl From<Handler> for Action {
+    #[inline]
+    fn from(handler: Handler) -> Self { Action { handler, mask: SigSet::empty(), flags: SigFlags::empty() } }
+}
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86", target_arch = "x86_64", target_arch = "arm", target_arch = "aarch64",
+                                    target_arch = "riscv64", target_arch = "powerpc64", target_arch = "s390x")))]
+#[repr(C)]
+struct k_sigaction {
+    handler: usize,
+    flags: libc::c_ulong,
+    restorer: usize,
+    mask: SigSet,
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+const SA_RESTORER: libc::c_ulong = 0x04000000;
+
+// The kernel returns from a handler through the restorer, which must invoke `rt_sigreturn` with the stack as it left it.
+// It is a Rust function, so its symbol is mangled and can not clash with that of another version of this crate.
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[unsafe(naked)]
+unsafe extern "C" fn restore() {
+    ::core::arch::naked_asm!("mov eax, 15", "syscall")
+}
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86", target_arch = "x86_64", target_arch = "arm", target_arch = "aarch64",
+                                    target_arch = "riscv64", target_arch = "powerpc64", target_arch = "s390x")))]
+impl k_sigaction {
+    #[inline]
+    fn from_action(act: Action) -> Self {
+        let mut flags = act.flags.bits() as libc::c_ulong;
+        let handler = match act.handler {
+            Handler::Default => libc::SIG_DFL,
+            Handler::Ignore  => libc::SIG_IGN,
+            Handler::Handler(f) => f as usize,
+            Handler::Action(f) => { flags |= libc::SA_SIGINFO as libc::c_ulong; f as usize },
+        };
+        #[cfg(target_arch = "x86_64")]
+        let restorer = { flags |= SA_RESTORER; restore as usize };
+        #[cfg(not(target_arch = "x86_64"))]
+        let restorer = 0;
+        k_sigaction { handler, flags, restorer, mask: act.mask }
+    }
+
+    #[inline]
+    unsafe fn to_action(&self) -> Action {
+        let flags = SigFlags::from_bits_truncate(self.flags as u32);
+        Action {
+            handler: match self.handler {
+                libc::SIG_DFL => Handler::Default,
+                libc::SIG_IGN => Handler::Ignore,
+                f if flags.contains(SigFlags::SigInfo) => Handler::Action(mem::transmute(f)),
+                f => Handler::Handler(mem::transmute(f)),
+            },
+            mask: self.mask,
+            flags,
+        }
+    }
+}
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86", target_arch = "x86_64", target_arch = "arm", target_arch = "aarch64",
+                                    target_arch = "riscv64", target_arch = "powerpc64", target_arch = "s390x")))]
+/// Set the action to take on receipt of `sig`, and return the former action.
+///
+/// # Safety
+///
+/// A handler may be called at any point of the program, so it must only do what is safe in that case,
+/// conventionally only calling async-signal-safe functions.
+#[inline]
+pub unsafe fn set_action(sig: Signal, act: Action) -> Result<Action, Error> {
+    let new = k_sigaction::from_action(act);
+    let mut old = MU::<k_sigaction>::uninit();
+    esyscall!(RT_SIGACTION, sig.0, &new as *const _, old.as_mut_ptr(), mem::size_of::<SigSet>())?;
+    Ok(old.assume_init().to_action())
+}
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86", target_arch = "x86_64", target_arch = "arm", target_arch = "aarch64",
+                                    target_arch = "riscv64", target_arch = "powerpc64", target_arch = "s390x")))]
+/// Return the action taken on receipt of `sig`.
+#[inline]
+pub fn action(sig: Signal) -> Result<Action, Error> { unsafe {
+    let mut old = MU::<k_sigaction>::uninit();
+    esyscall!(RT_SIGACTION, sig.0, ptr::null::<k_sigaction>(), old.as_mut_ptr(), mem::size_of::<SigSet>())?;
+    Ok(old.assume_init().to_action())
+} }
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86", target_arch = "x86_64", target_arch = "arm", target_arch = "aarch64",
+                                    target_arch = "riscv64", target_arch = "powerpc64", target_arch = "s390x")))]
+/// Ignore `sig`.
+#[inline]
+pub fn ignore(sig: Signal) -> Result<(), Error> {
+    unsafe { set_action(sig, Action::from(Handler::Ignore)) }.map(|_| ())
+}
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86", target_arch = "x86_64", target_arch = "arm", target_arch = "aarch64",
+                                    target_arch = "riscv64", target_arch = "powerpc64", target_arch = "s390x")))]
+/// Take the default action on receipt of `sig`.
+#[inline]
+pub fn reset(sig: Signal) -> Result<(), Error> {
+    unsafe { set_action(sig, Action::from(Handler::Default)) }.map(|_| ())
+}
+
+/// How to modify the signal mask
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MaskHow {
+    /** Block the given signals, in addition to those already blocked */ Block,
+    /** Unblock the given signals                                      */ Unblock,
+    /** Block exactly the given signals                                */ Set,
+}
+
+#[cfg(target_os = "linux")]
+/// Modify the signal mask of the calling thread, and return the former mask.
+#[inline]
+pub fn set_mask(how: MaskHow, set: SigSet) -> Result<SigSet, Error> { unsafe {
+    let how = match how {
+        MaskHow::Block   => libc::SIG_BLOCK,
+        MaskHow::Unblock => libc::SIG_UNBLOCK,
+        MaskHow::Set     => libc::SIG_SETMASK,
+    };
+    let mut old = MU::<SigSet>::uninit();
+    esyscall!(RT_SIGPROCMASK, how, &set as *const _, old.as_mut_ptr(), mem::size_of::<SigSet>())?;
+    Ok(old.assume_init())
+} }
+
+#[cfg(target_os = "linux")]
+/// Return the signal mask of the calling thread.
+#[inline]
+pub fn mask() -> Result<SigSet, Error> { unsafe {
+    let mut old = MU::<SigSet>::uninit();
+    esyscall!(RT_SIGPROCMASK, 0, ptr::null::<SigSet>(), old.as_mut_ptr(), mem::size_of::<SigSet>())?;
+    Ok(old.assume_init())
+} }
+
+/// Send `sig` to the process `pid`.
+#[inline]
+pub fn kill(pid: Id, sig: Signal) -> Result<(), Error> {
+    unsafe { esyscall_!(KILL, pid, sig.0) }
+}
+
+/// Send `sig` to every process in the group `gid`.
+#[inline]
+pub fn kill_group(gid: Id, sig: Signal) -> Result<(), Error> {
+    unsafe { esyscall_!(KILL, -(gid as isize), sig.0) }
+}
+
+#[cfg(target_os = "linux")]
+/// Send `sig` to the thread `tid` in the thread group `tgid`.
+#[inline]
+pub fn tgkill(tgid: Id, tid: Id, sig: Signal) -> Result<(), Error> {
+    unsafe { esyscall_!(TGKILL, tgid, tid, sig.0) }
+}
+
+#[cfg(target_os = "linux")]
+/// Signal file: a `File` from which to read signals as `SigInfo` records
+///
+/// The signals should be blocked, else they may be delivered by their action rather than to the file.
+/// The file is readable when some such signal is pending, so it can be polled.
+#[derive(Debug)]
+pub struct SignalFile(File);
+
+#[cfg(target_os = "linux")]
+impl SignalFile {
+    /// Make a new signal file, which accepts the signals in `set`.
+    ///
+    /// Only `O_CLOEXEC` and `O_NONBLOCK` of `flags` are meaningful.
+    #[inline]
+    pub fn new(set: SigSet, flags: OpenFlags) -> Result<Self, Error> {
+        unsafe { esyscall!(SIGNALFD4, -1isize, &set as *const _, mem::size_of::<SigSet>(),
+                           (flags & (OpenFlags::O_CLOEXEC | OpenFlags::O_NONBLOCK)).bits()) }
+            .map(|fd| SignalFile(File::new_unchecked(fd as _)))
+    }
+
+    /// Modify which signals the file accepts.
+    #[inline]
+    pub fn set_mask(&self, set: SigSet) -> Result<(), Error> {
+        unsafe { esyscall_!(SIGNALFD4, self.0.fd(), &set as *const _, mem::size_of::<SigSet>(), 0) }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Deref for SignalFile {
+    type Target = File;
+    #[inline]
+    fn deref(&self) -> &File { &self.0 }
+}
+
+#[cfg(target_os = "linux")]
+impl Read<SigInfo> for SignalFile {
+    type Err = Error;
+
+    #[inline]
+    fn read(&mut self, buf: &mut [SigInfo]) -> Result<usize, Error> {
+        unsafe { esyscall!(READ, self.0.fd(), buf.as_mut_ptr(), buf.len() * mem::size_of::<SigInfo>()) }
+            .map(|n| n / mem::size_of::<SigInfo>())
+    }
+}
+
+#[cfg(target_os = "linux")]
+/// Information about a received signal
+#[repr(transparent)]
+#[derive(Clone, Copy)]
+pub struct SigInfo(libc::signalfd_siginfo);
+
+#[cfg(target_os = "linux")]
+impl SigInfo {
+    /** Signal                                   */ #[inline] pub fn signal(&self) -> Signal { Signal(self.0.ssi_signo as _) }
+    /** Cause of the signal                      */ #[inline] pub fn code(&self) -> libc::c_int { self.0.ssi_code }
+    /** Error number, if any                     */ #[inline] pub fn errno(&self) -> libc::c_int { self.0.ssi_errno }
+    /** ID of the sending process                */ #[inline] pub fn pid(&self) -> Id { self.0.ssi_pid as _ }
+    /** Real user ID of the sending process      */ #[inline] pub fn uid(&self) -> libc::uid_t { self.0.ssi_uid as _ }
+    /** Exit status or signal, for `SIGCHLD`     */ #[inline] pub fn status(&self) -> libc::c_int { self.0.ssi_status }
+    /** File descriptor, for `SIGIO`             */ #[inline] pub fn fd(&self) -> isize { self.0.ssi_fd as _ }
+    /** Faulting address, for `SIGSEGV` et al    */ #[inline] pub fn addr(&self) -> usize { self.0.ssi_addr as _ }
+    /** Value sent with `sigqueue`, as integer   */ #[inline] pub fn int(&self) -> libc::c_int { self.0.ssi_int }
+    /** Value sent with `sigqueue`, as pointer   */ #[inline] pub fn ptr(&self) -> usize { self.0.ssi_ptr as _ }
+}
+
+#[cfg(target_os = "linux")]
+impl fmt::Debug for SigInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SigInfo").field("signal", &self.signal()).field("code", &self.code())
+         .field("errno", &self.errno()).field("pid", &self.pid()).field("uid", &self.uid())
+         .field("status", &self.status()).finish()
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use process;
+    use super::*;
+
+    #[test]
+    fn signal_file() {
+        let sig = Signal::SIGUSR1;
+        let old = set_mask(MaskHow::Block, SigSet::from(sig)).unwrap();
+        let mut sf = SignalFile::new(SigSet::from(sig), OpenFlags::O_CLOEXEC | OpenFlags::O_NONBLOCK).unwrap();
+        let mut si = [unsafe { mem::zeroed::<SigInfo>() }];
+        assert_eq!(Err(Error::EAGAIN), sf.read(&mut si));
+
+        // Send it to this thread, as other threads may not block it.
+        let tid = unsafe { syscall!(GETTID) } as Id;
+        tgkill(process::pid(), tid, sig).unwrap();
+        assert!(mask().unwrap().contains(sig));
+        assert_eq!(1, sf.read(&mut si).unwrap());
+        assert_eq!(sig, si[0].signal());
+        assert_eq!(process::pid(), si[0].pid());
+        set_mask(MaskHow::Set, old).unwrap();
+    }
+
+    #[test]
+    fn sig_set() {
+        let set = SigSet::from(Signal::SIGINT) | Signal::SIGTERM;
+        assert!(set.contains(Signal::SIGTERM));
+        assert!(!set.contains(Signal::SIGHUP));
+        assert_eq!(2, set.iter().count());
+        assert_eq!(None, Signal::new(0));
+        assert_eq!(Some(Signal::SIGKILL), Signal::new(libc::SIGKILL));
+        assert_eq!(None, Signal::rt((SIGRTMAX - SIGRTMIN + 1) as usize));
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "arm", target_arch = "aarch64",
+              target_arch = "riscv64", target_arch = "powerpc64", target_arch = "s390x"))]
+    #[test]
+    fn handler() {
+        use core::sync::atomic::{AtomicBool, Ordering};
+        static HIT: AtomicBool = AtomicBool::new(false);
+        extern "C" fn handle(_: Signal) { HIT.store(true, Ordering::SeqCst) }
+
+        let sig = Signal::SIGUSR2;
+        let old = unsafe { set_action(sig, Action::from(Handler::Handler(handle))) }.unwrap();
+        let tid = unsafe { syscall!(GETTID) } as Id;
+        tgkill(process::pid(), tid, sig).unwrap();
+        // Only returning through the restorer lets this go on.
+        assert!(HIT.load(Ordering::SeqCst));
+        match action(sig).unwrap().handler {
+            Handler::Handler(f) => assert_eq!(handle as usize, f as usize),
+            h => panic!("{:?}", h),
+        }
+        unsafe { set_action(sig, old) }.unwrap();
+    }
+}
@@ -33,6 +33,11 @@ impl EpochTime {
         EpochTime(0) + Span::from(ts)
     }
 
+    #[inline]
+    pub(crate) fn to_c_timespec(self) -> Option<::libc::timespec> {
+        (self - EpochTime(0)).to_c_timespec()
+    }
+
     #[inline]
     pub(crate) fn from_s_ns(s: ::libc::time_t, ns: ::libc::c_long) -> Self {
         Self::from_c_timespec(::libc::timespec { tv_sec: s, tv_nsec: ns as _ })
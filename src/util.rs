@@ -4,3 +4,19 @@ pub fn try_to_usize(n: u64) -> Result<usize, Error> {
     let m = n as usize;
     if m as u64 == n { Ok(m) } else { Err(Error::EOVERFLOW) }
 }
+
+/// Numbers of system calls too new to be in `syscall::nr`
+#[cfg(target_os = "linux")]
+pub mod nr {
+    #[cfg(target_arch = "mips")]
+    const base: usize = 4000;
+    #[cfg(target_arch = "mips64")]
+    const base: usize = 5000;
+    #[cfg(not(any(target_arch = "mips", target_arch = "mips64")))]
+    const base: usize = 0;
+
+    pub const PIDFD_SEND_SIGNAL: usize = base + 424;
+    pub const PIDFD_OPEN       : usize = base + 434;
+    pub const OPENAT2          : usize = base + 437;
+    pub const FACCESSAT2       : usize = base + 439;
+}